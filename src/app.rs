@@ -1,132 +1,693 @@
 use crate::{
-    components::linechart::{LineChartData, LineChartsList},
+    components::{
+        clientsettings::ClientSettings, dailycards::DailyCards, linechart::LineChartsList,
+        resampleselect::ResampleSelect, skeleton::ChartSkeletons, timezoneselect::TimezoneSelect,
+        unitsettings::UnitSettings,
+    },
     map::GoogleMap,
 };
+use chrono::{DateTime, Duration, Utc};
+use gloo::timers::callback::Timeout;
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
 use wasm_bindgen::JsCast;
 
 use wasm_bindgen::prelude::Closure;
 use wasm_bindgen_futures::spawn_local;
-use web_sys::{console, window};
+use web_sys::{console, window, HtmlInputElement, PositionOptions};
 use yew::prelude::*;
 
 use crate::{
-    helpers::GeolocationPosition,
-    xml::{
-        generic::parse_xml,
-        locationforecast::{flatten_response, get_weather, prepare_plot_data},
+    helpers::{GeolocationPosition, GeolocationPositionError},
+    xml::locationforecast::{
+        cache,
+        client::{cancellable, ForecastClient, ForecastClientConfig},
+        compact_forecast,
+        daily::daily_summaries,
+        error::ForecastError,
+        location::GridPoint,
+        nearest_location, prepare_plot_data, stream_forecast,
+        timezone::DisplayZone,
+        units::Units,
+        FlatForecast, PlotData, PlotOptions,
     },
 };
 
-/// Attemps to fetch the user's geolocation position using the browser API
-fn get_geo(position: &UseStateHandle<Option<(f64, f64)>>) -> impl Fn(&()) {
-    let geolocation_handle = window()
-        .expect("Failed to get window")
-        .navigator()
-        .geolocation()
-        .expect("Failed to get Geolocation handle");
+/// How long the map has to go without another click before we fetch for the new spot
+const SELECT_DEBOUNCE_MS: u32 = 400;
+
+/// A forecast older than this gets a warning that it may be out of date
+const STALE_AFTER_MINUTES: i64 = 60;
+
+/// How long to give the browser to find where we are
+const GEO_TIMEOUT_MS: u32 = 15_000;
+
+/// How old a position the browser can give us instead of finding a new one
+const GEO_MAXIMUM_AGE_MS: u32 = 5 * 60 * 1000;
+
+/// How many placeholder charts to show while loading
+const SKELETON_CHARTS: usize = 6;
+
+/// The user's selected (latitude, longitude), if we have one yet
+type PositionHandle = UseStateHandle<Option<(f64, f64)>>;
+
+/// Where we're at with getting a forecast to show
+#[derive(Debug, Clone, PartialEq)]
+pub enum ForecastState {
+    /// No position yet, and nothing to do until one is picked on the map
+    Idle,
+    /// Waiting for the browser to tell us where we are
+    Locating,
+    /// Waiting on the forecast service, with nothing saved to show in the meantime
+    Fetching,
+    /// A forecast, and when it came from (or was last confirmed by) the server
+    Ready {
+        forecast: Rc<FlatForecast>,
+        fetched_at: DateTime<Utc>,
+        /// Why a saved forecast couldn't be brought up to date, if it couldn't
+        refresh_error: Option<ForecastError>,
+    },
+    Failed(ForecastError),
+}
+
+type StateHandle = UseStateHandle<ForecastState>;
+
+impl ForecastState {
+    pub fn forecast(&self) -> Option<Rc<FlatForecast>> {
+        match self {
+            Self::Ready { forecast, .. } => Some(forecast.clone()),
+            _ => None,
+        }
+    }
+
+    /// When the forecast shown was fetched, if it's old enough to warn about
+    pub fn stale_since(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Ready { fetched_at, .. }
+                if now - *fetched_at > Duration::minutes(STALE_AFTER_MINUTES) =>
+            {
+                Some(*fetched_at)
+            }
+            _ => None,
+        }
+    }
+
+    /// When the forecast shown will get old enough to warn about
+    pub fn stale_at(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Ready { fetched_at, .. } => {
+                Some(*fetched_at + Duration::minutes(STALE_AFTER_MINUTES))
+            }
+            _ => None,
+        }
+    }
+
+    /// The saved forecast shown and why it couldn't be refreshed, if that's what happened
+    pub fn refresh_error(&self) -> Option<(DateTime<Utc>, &ForecastError)> {
+        match self {
+            Self::Ready {
+                fetched_at,
+                refresh_error: Some(err),
+                ..
+            } => Some((*fetched_at, err)),
+            _ => None,
+        }
+    }
+}
+
+/// Options for asking the browser where we are. Following is for when on the move, so it's
+/// worth the battery for a precise, up to date fix.
+fn position_options(follow: bool) -> PositionOptions {
+    let options = PositionOptions::new();
+    options.set_enable_high_accuracy(follow);
+    options.set_timeout(GEO_TIMEOUT_MS);
+    options.set_maximum_age(if follow { 0 } else { GEO_MAXIMUM_AGE_MS });
+    options
+}
 
+/// Explains a `GeolocationPositionError` code
+fn describe_geo_error(code: u16) -> &'static str {
+    match code {
+        1 => "permission was denied",
+        2 => "the position isn't available",
+        3 => "it took too long",
+        _ => "something went wrong",
+    }
+}
+
+/// Whether a new fix while following is worth moving the forecast for. Moves that wouldn't
+/// change the cache key, or that are within the fix's own accuracy, are mostly GPS jitter.
+fn moved_enough(last: (f64, f64), next: (f64, f64), accuracy_m: f64) -> bool {
+    let last_point = GridPoint {
+        latitude: Some(last.0),
+        longitude: Some(last.1),
+        ..Default::default()
+    };
+    let moved_m = last_point
+        .distance_km(next.0, next.1)
+        .unwrap_or(f64::INFINITY)
+        * 1000.;
+    !cache::same_key_position(last, next) && moved_m > accuracy_m
+}
+
+/// Attemps to fetch the user's geolocation position using the browser API, once when there's
+/// no position yet or, when following, every time it changes. Without geolocation we stay
+/// idle until a spot is picked on the map. A spot picked on the map always wins over a fix
+/// that turns up after it.
+fn get_geo(
+    position: &PositionHandle,
+    accuracy: &UseStateHandle<Option<f64>>,
+    geo_error: &UseStateHandle<Option<String>>,
+    picked: &Rc<RefCell<bool>>,
+    state: &StateHandle,
+) -> impl Fn(&bool) -> Box<dyn FnOnce()> {
     let position = position.clone();
+    let accuracy = accuracy.clone();
+    let geo_error = geo_error.clone();
+    let picked = picked.clone();
+    let state = state.clone();
 
-    move |_| {
-        let position = position.clone();
+    move |follow| {
+        let follow = *follow;
+        // Turning following off leaves the forecast wherever it is
+        if !follow && position.is_some() {
+            return Box::new(|| ());
+        }
+        // Turning it on means the user wants to be followed again
+        if follow {
+            *picked.borrow_mut() = false;
+        }
+        let geolocation_handle = window().and_then(|window| window.navigator().geolocation().ok());
+        let Some(geolocation_handle) = geolocation_handle else {
+            geo_error.set(Some("Geolocation isn't available".to_string()));
+            return Box::new(|| ());
+        };
 
-        // This closure is compiled into JS (I think?). It's called when we successfully
-        // retrieve the user location from the Geolocation browser API
-        let geoloc_callback =
+        // These closures are compiled into JS (I think?). They're called when we retrieve the
+        // user location from the Geolocation browser API, or fail to
+        let on_success = {
+            let position = position.clone();
+            let accuracy = accuracy.clone();
+            let geo_error = geo_error.clone();
+            let picked = picked.clone();
+            // Where the forecast was last moved to by this watch
+            let last_fix = Rc::new(Cell::new(None));
             Closure::<dyn Fn(GeolocationPosition)>::new(move |pos: GeolocationPosition| {
                 let coords = pos.coords();
                 let lat = coords.latitude();
                 let lon = coords.longitude();
 
-                console::log_1(&format!("Got location: {:?} {:?}", lat, lon).into());
-                if lat != 0. || lon != 0. {
+                console::log_1(
+                    &format!(
+                        "Got location: {:?} {:?}, within {:.0}m",
+                        lat,
+                        lon,
+                        coords.accuracy()
+                    )
+                    .into(),
+                );
+                if *picked.borrow() {
+                    return;
+                }
+                let moved = last_fix
+                    .get()
+                    .is_none_or(|last| moved_enough(last, (lat, lon), coords.accuracy()));
+                if (lat != 0. || lon != 0.) && moved {
+                    last_fix.set(Some((lat, lon)));
                     position.set(Some((lat, lon)));
+                    accuracy.set(Some(coords.accuracy()));
+                    geo_error.set(None);
                 }
-            });
+            })
+        };
+        let on_error = {
+            let geo_error = geo_error.clone();
+            Closure::<dyn Fn(GeolocationPositionError)>::new(
+                move |err: GeolocationPositionError| {
+                    console::warn_1(&format!("Geolocation failed: {}", err.message()).into());
+                    geo_error.set(Some(format!(
+                        "Couldn't get your location, {}",
+                        describe_geo_error(err.code())
+                    )));
+                },
+            )
+        };
+
+        let options = position_options(follow);
+        let success = on_success.as_ref().unchecked_ref();
+        let error = Some(on_error.as_ref().unchecked_ref());
+        let watch_id = if follow {
+            geolocation_handle
+                .watch_position_with_error_callback_and_options(success, error, &options)
+                .map(Some)
+        } else {
+            geolocation_handle
+                .get_current_position_with_error_callback_and_options(success, error, &options)
+                .map(|_| None)
+        };
+        match &watch_id {
+            Ok(_) if position.is_none() => state.set(ForecastState::Locating),
+            Ok(_) => {}
+            Err(err) => {
+                console::warn_2(&"Couldn't ask for the location:".into(), err);
+                geo_error.set(Some("Couldn't ask for your location".to_string()));
+            }
+        }
 
-        geolocation_handle
-            .get_current_position(geoloc_callback.as_ref().unchecked_ref())
-            .expect("Geolocation function failed.");
-        // We need to leak the closure here so it lives long enough. This should only be
-        // called once since we have dependencies == ()
-        geoloc_callback.forget();
+        // The callbacks have to live as long as the browser might call them, so they're kept
+        // here until the app goes away or following is switched on or off
+        Box::new(move || {
+            if let Ok(Some(watch_id)) = watch_id {
+                geolocation_handle.clear_watch(watch_id);
+            }
+            drop((on_success, on_error));
+        })
     }
 }
 
 // This block reaches out to the MET Eireann API to get weather information at the user's
-// location. It then post-processes the data into plot-ready data. Getting the compiler to like
-// this function was a pain in the hole.
-fn fetch_plot_data(
-    base_url: &str,
-    position: &UseStateHandle<Option<(f64, f64)>>,
-    plot_data: &UseStateHandle<Vec<LineChartData>>,
-) -> impl Fn(&UseStateHandle<Option<(f64, f64)>>) {
+// location. Getting the compiler to like this function was a pain in the hole.
+fn fetch_forecast(
+    client: &Rc<ForecastClient>,
+    generation: &Rc<RefCell<u32>>,
+    last_retries: &Rc<RefCell<u32>>,
+    position: &PositionHandle,
+    state: &StateHandle,
+) -> impl Fn(&(PositionHandle, u32, ForecastClientConfig)) -> Box<dyn FnOnce()> {
     // We have to clone a bunch of times :(
-    let base_url = base_url.to_owned();
+    let client = client.clone();
+    let generation = generation.clone();
+    let last_retries = last_retries.clone();
     let position = position.clone();
-    let plot_data = plot_data.clone();
+    let state = state.clone();
+
+    move |(_, retries, _)| {
+        // Anything started before this point is out of date now
+        *generation.borrow_mut() += 1;
+        let this_generation = *generation.borrow();
+        // Retry and Refresh ask the server even when what's saved hasn't expired yet
+        let forced = std::mem::replace(&mut *last_retries.borrow_mut(), *retries) != *retries;
 
-    move |_| {
         // Only execute if we've got a valid position
-        if let Some((lat, lon)) = *position {
-            let base_url = base_url.clone();
-            let plot_data = plot_data.clone();
-
-            spawn_local(async move {
-                console::log_1(&format!("Fetching weather at: {:?} {:?}", lat, lon).into());
-                let mut chart_data = get_weather(base_url.as_str(), lat, lon)
-                    .await
-                    .map(|xml| parse_xml(&xml))
-                    .map(|xml| flatten_response(&xml))
-                    .map(|items| prepare_plot_data(&items))
-                    .expect("Failed to get weather data");
-                chart_data.sort_by_key(|x| x.key.clone());
-
-                plot_data.set(chart_data);
+        let Some((lat, lon)) = *position else {
+            return Box::new(|| ());
+        };
+        let client = client.clone();
+        let generation = generation.clone();
+        let state = state.clone();
+
+        // Show whatever we have saved straight away, and skip the request if it's still
+        // in date. A saved copy that doesn't parse is ignored and fetched again.
+        let key = cache::cache_key(client.config(), lat, lon);
+        let cached = cache::load(&key)
+            .and_then(|entry| Some((read_forecast(&entry.xml, lat, lon).ok()?, entry)));
+        let (cached, showing) = if let Some((data, entry)) = cached {
+            console::log_1(&format!("Using saved forecast from {}", entry.fetched_at).into());
+            let data = Rc::new(data);
+            state.set(ForecastState::Ready {
+                forecast: data.clone(),
+                fetched_at: entry.fetched_at,
+                refresh_error: None,
             });
-        }
+            if entry.is_fresh(Utc::now()) && !forced {
+                return Box::new(|| ());
+            }
+            let fetched_at = entry.fetched_at;
+            (Some(entry), Some((data, fetched_at)))
+        } else {
+            state.set(ForecastState::Fetching);
+            (None, None)
+        };
+
+        let (request, abort) = cancellable(async move {
+            console::log_1(&format!("Fetching weather at: {:?} {:?}", lat, lon).into());
+            let mut entry = client.get_weather(lat, lon, cached.as_ref()).await?;
+            let data = read_forecast(&entry.xml, lat, lon)?;
+            // Only keep responses that made sense, and only the part that's shown, since whole
+            // responses can be too big for localStorage
+            if let Some(item) = data.items.first() {
+                match compact_forecast(&entry.xml, &item.location) {
+                    Ok(xml) => entry.xml = xml,
+                    Err(err) => console::warn_1(
+                        &format!("Couldn't shrink the forecast for saving: {}", err).into(),
+                    ),
+                }
+            }
+            cache::store(&key, &entry);
+            Ok((data, entry.fetched_at))
+        });
+        spawn_local(async move {
+            let result = request.await;
+            // Aborting should have stopped it, but make sure a late finisher can't overwrite
+            // the forecast for wherever was picked after it
+            if *generation.borrow() != this_generation {
+                return;
+            }
+            match result {
+                Ok((data, fetched_at)) => state.set(ForecastState::Ready {
+                    forecast: Rc::new(data),
+                    fetched_at,
+                    refresh_error: None,
+                }),
+                // Something newer has taken over, so leave the display alone
+                Err(ForecastError::Cancelled) => {}
+                Err(err) => {
+                    console::error_1(&format!("Failed to get weather data: {}", err).into());
+                    // If the refresh fails, what's saved is better than nothing
+                    match showing {
+                        Some((forecast, fetched_at)) => state.set(ForecastState::Ready {
+                            forecast,
+                            fetched_at,
+                            refresh_error: Some(err),
+                        }),
+                        None => state.set(ForecastState::Failed(err)),
+                    }
+                }
+            }
+        });
+
+        // Called off if the position, retry count or config change, or the app goes away
+        Box::new(move || abort.abort())
+    }
+}
+
+/// Parses a response, keeping only the location closest to the requested position
+fn read_forecast(xml: &str, lat: f64, lon: f64) -> Result<FlatForecast, ForecastError> {
+    stream_forecast(xml).map(|data| FlatForecast {
+        // There's usually only one location, but if not use the closest
+        items: nearest_location(data.items, lat, lon),
+        metadata: data.metadata,
+    })
+}
+
+/// Post-processes the forecast into plot-ready data
+fn plot_forecast(forecast: &FlatForecast, options: &PlotOptions) -> PlotData {
+    let mut chart_data = prepare_plot_data(&forecast.items, options);
+    chart_data.charts.sort_by_key(|x| x.key.clone());
+
+    if !chart_data.generic.is_empty() {
+        console::warn_1(
+            &format!(
+                "Plotting unrecognised measurements with guessed units: {}",
+                chart_data.generic.join(", ")
+            )
+            .into(),
+        );
+    }
+    if !chart_data.skipped.is_empty() {
+        console::warn_1(
+            &format!(
+                "Skipped measurements with no plottable values: {}",
+                chart_data.skipped.join(", ")
+            )
+            .into(),
+        );
+    }
+    if let Some(location) = &chart_data.location {
+        console::log_1(&format!("Forecast grid point: {}", location).into());
     }
+
+    chart_data
 }
 
 #[function_component(App)]
 pub fn app() -> Html {
-    let base_url =
-        "https://cors-anywhere.herokuapp.com/http://openaccess.pf.api.met.ie/metno-wdb2ts";
+    // Defaults come from the build environment, and can be changed in the settings panel
+    let client_config = use_state(ForecastClientConfig::from_build_env);
+    let client = use_memo((*client_config).clone(), |config| {
+        ForecastClient::new(config.clone())
+    });
 
     // Get geolocation data
     let position = use_state(|| None);
-    use_effect_with((), get_geo(&position));
+    let accuracy = use_state(|| None);
+    let geo_error = use_state(|| None);
+    let state = use_state(|| ForecastState::Idle);
+    // Keep moving the forecast with the user, rather than only looking once
+    let follow = use_state(|| false);
+    // Set once a spot is picked on the map, so a location that arrives late can't replace it
+    let picked = use_mut_ref(|| false);
+    use_effect_with(
+        *follow,
+        get_geo(&position, &accuracy, &geo_error, &picked, &state),
+    );
+    // Stop waiting on a location that isn't coming
+    {
+        let state = state.clone();
+        use_effect_with((*geo_error).clone(), move |geo_error| {
+            if geo_error.is_some() && *state == ForecastState::Locating {
+                state.set(ForecastState::Idle);
+            }
+        });
+    }
 
-    // Fetch weather data & prepare for plotting
-    let plot_data = use_state(Vec::new);
+    // Fetch weather data
+    // Bumped by the retry button to re-run the fetch for the same position
+    let retries = use_state(|| 0);
+    // Bumped for every fetch, so results for an old position can be told apart
+    let generation = use_mut_ref(|| 0);
+    // What `retries` was for the last fetch, so a retry can be told apart from a move
+    let last_retries = use_mut_ref(|| 0);
     use_effect_with(
-        position.clone(),
-        fetch_plot_data(base_url, &position, &plot_data),
+        (position.clone(), *retries, (*client_config).clone()),
+        fetch_forecast(&client, &generation, &last_retries, &position, &state),
     );
 
+    // The stale warning depends on the time as well as the state, so re-render once the
+    // forecast shown gets old enough for it
+    let force_update = use_force_update();
+    use_effect_with(state.stale_at(), move |stale_at| {
+        let timeout = stale_at.map(|stale_at| {
+            let wait = (stale_at - Utc::now())
+                .num_milliseconds()
+                .clamp(0, u32::MAX as i64);
+            Timeout::new(wait as u32, move || force_update.force_update())
+        });
+        move || drop(timeout)
+    });
+
+    // Prepare for plotting. Changing units or timezone only redoes this part, not the fetch.
+    let units = use_state(Units::default);
+    let timezone = use_state(DisplayZone::default);
+    // Only changes how the charts are drawn, so it isn't part of the plot options
+    let resample = use_state(|| None);
+    let forecast = state.forecast();
+    let options = PlotOptions {
+        units: *units,
+        timezone: *timezone,
+    };
+    let plot_data = use_memo((forecast.clone(), options), |(forecast, options)| {
+        forecast
+            .as_ref()
+            .map(|forecast| plot_forecast(forecast, options))
+            .unwrap_or_default()
+    });
+    let days = use_memo((forecast.clone(), *timezone), |(forecast, timezone)| {
+        forecast
+            .as_ref()
+            .map(|forecast| daily_summaries(&forecast.items, timezone))
+            .unwrap_or_default()
+    });
+    let handle_units_change = {
+        let units = units.clone();
+        Callback::from(move |new_units| units.set(new_units))
+    };
+    let handle_timezone_change = {
+        let timezone = timezone.clone();
+        Callback::from(move |new_timezone| timezone.set(new_timezone))
+    };
+
+    let handle_resample_change = {
+        let resample = resample.clone();
+        Callback::from(move |new_resample| resample.set(new_resample))
+    };
+
+    let handle_client_config_change = {
+        let client_config = client_config.clone();
+        Callback::from(move |new_config| client_config.set(new_config))
+    };
+
+    let handle_retry = {
+        let retries = retries.clone();
+        Callback::from(move |_| retries.set(*retries + 1))
+    };
+
     //let selected_coordinates = use_state(|| (53.362688, -6.3111168));
+    // Clicking around the map only fetches once it settles. Replacing the timeout cancels
+    // the one before.
+    let pending_select = use_mut_ref(|| None::<Timeout>);
     let handle_location_select = {
         let position = position.clone();
+        let accuracy = accuracy.clone();
+        let follow = follow.clone();
+        let picked = picked.clone();
         Callback::from(move |coords: (f64, f64)| {
-            console::log_1(&format!("Setting coordinates to: {:?}", coords).into());
-            position.set(Some(coords));
+            // Picking a spot by hand means the user wants that one, not wherever they are
+            *picked.borrow_mut() = true;
+            follow.set(false);
+            let position = position.clone();
+            let accuracy = accuracy.clone();
+            *pending_select.borrow_mut() = Some(Timeout::new(SELECT_DEBOUNCE_MS, move || {
+                console::log_1(&format!("Setting coordinates to: {:?}", coords).into());
+                position.set(Some(coords));
+                accuracy.set(None);
+            }));
+        })
+    };
+    let handle_follow_change = {
+        let follow = follow.clone();
+        Callback::from(move |event: Event| {
+            follow.set(event.target_unchecked_into::<HtmlInputElement>().checked())
         })
     };
 
-    let location_text = if let Some((lat, lon)) = *position.clone() {
-        format!("Location: lat={}, lon={}", lat, lon)
-    } else {
-        "Enable geolocation permissions!".to_string()
+    let location_text = match (*position, &*state) {
+        (Some((lat, lon)), _) => match *accuracy {
+            Some(accuracy) => format!("Location: lat={}, lon={} (±{:.0}m)", lat, lon, accuracy),
+            None => format!("Location: lat={}, lon={}", lat, lon),
+        },
+        (None, ForecastState::Locating) => "Finding your location...".to_string(),
+        (None, _) => "Pick a location on the map to see its forecast".to_string(),
     };
+    // So people know how fresh the forecast is, and when it's worth refreshing
+    let metadata_text = forecast
+        .as_ref()
+        .and_then(|forecast| forecast.metadata.summary(&timezone));
+
     html! {
         <>
-            <div>{ location_text }</div>
+            <header>
+                <div>{ location_text }</div>
+                if let Some(geo_error) = &*geo_error {
+                    <div class="error">{ geo_error }</div>
+                }
+                <label class="diagnostics">
+                    <input type="checkbox" checked={*follow} onchange={handle_follow_change} />
+                    { " Follow my location" }
+                </label>
+                if let Some(metadata_text) = metadata_text {
+                    <div class="diagnostics">{ metadata_text }</div>
+                }
+            </header>
+            if let Some(grid_point) = &plot_data.location {
+                <div class="diagnostics">{ format!("Forecast for grid point {}", grid_point) }</div>
+            }
+            if let ForecastState::Failed(err) = &*state {
+                <div class="error">
+                    { format!("Failed to get weather data. {}", err) }
+                    <button onclick={handle_retry.clone()}>{ "Retry" }</button>
+                </div>
+            }
+            if let Some((fetched_at, err)) = state.refresh_error() {
+                <div class="stale">
+                    { format!(
+                        "Couldn't refresh the forecast from {}. {}",
+                        timezone.format(&fetched_at, "%a %-d %b %H:%M"),
+                        err
+                    ) }
+                    <button onclick={handle_retry.clone()}>{ "Retry" }</button>
+                </div>
+            } else if let Some(fetched_at) = state.stale_since(Utc::now()) {
+                <div class="stale">
+                    { format!(
+                        "Showing a forecast from {}, it may be out of date.",
+                        timezone.format(&fetched_at, "%a %-d %b %H:%M")
+                    ) }
+                    <button onclick={handle_retry}>{ "Refresh" }</button>
+                </div>
+            }
             <div id="map-container">
                 <GoogleMap on_location_select={handle_location_select.clone()} />
             </div>
-            <div>
-                <LineChartsList chart_data={(*plot_data).clone()} />
-            </div>
+            <UnitSettings units={*units} on_change={handle_units_change} />
+            <TimezoneSelect timezone={*timezone} on_change={handle_timezone_change} />
+            <ResampleSelect resample={*resample} on_change={handle_resample_change} />
+            <ClientSettings
+                config={(*client_config).clone()}
+                on_change={handle_client_config_change}
+            />
+            if !plot_data.skipped.is_empty() {
+                <div class="diagnostics">
+                    { format!("Not shown: {}", plot_data.skipped.join(", ")) }
+                </div>
+            }
+            if let ForecastState::Fetching = &*state {
+                <ChartSkeletons count={SKELETON_CHARTS} />
+            }
+            if forecast.is_some() {
+                <DailyCards days={(*days).clone()} units={*units} />
+                <div>
+                    <LineChartsList
+                        chart_data={plot_data.charts.clone()}
+                        symbols={plot_data.symbols.clone()}
+                        resample={*resample}
+                    />
+                </div>
+            }
         </>
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::xml::locationforecast::metadata::ForecastMetadata;
+
+    #[test]
+    fn test_forecast_state() {
+        let fetched_at = "2025-01-14T11:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let forecast = Rc::new(FlatForecast {
+            metadata: ForecastMetadata::default(),
+            items: vec![],
+        });
+        let ready = ForecastState::Ready {
+            forecast: forecast.clone(),
+            fetched_at,
+            refresh_error: None,
+        };
+
+        assert_eq!(ready.forecast(), Some(forecast.clone()));
+        assert_eq!(ready.stale_since(fetched_at + Duration::minutes(30)), None);
+        assert_eq!(
+            ready.stale_since(fetched_at + Duration::minutes(90)),
+            Some(fetched_at)
+        );
+        assert_eq!(ready.stale_at(), Some(fetched_at + Duration::minutes(60)));
+        assert_eq!(ready.refresh_error(), None);
+
+        // A failed refresh keeps the saved forecast, along with why
+        let err = ForecastError::Network("Failed to fetch data".to_string());
+        let refresh_failed = ForecastState::Ready {
+            forecast: forecast.clone(),
+            fetched_at,
+            refresh_error: Some(err.clone()),
+        };
+        assert_eq!(refresh_failed.forecast(), Some(forecast));
+        assert_eq!(refresh_failed.refresh_error(), Some((fetched_at, &err)));
+
+        let failed = ForecastState::Failed(ForecastError::Cancelled);
+        assert_eq!(failed.forecast(), None);
+        assert_eq!(failed.stale_since(fetched_at + Duration::days(1)), None);
+        assert_eq!(failed.stale_at(), None);
+    }
+
+    #[test]
+    fn test_moved_enough() {
+        let dublin = (53.3498, -6.2603);
+        // A few metres is jitter, even with a precise fix
+        assert!(!moved_enough(dublin, (53.3499, -6.2604), 5.));
+        // Across a cache key boundary, but not by more than the fix could be off by
+        assert!(!moved_enough(dublin, (53.3449, -6.2603), 1000.));
+        assert!(moved_enough(dublin, (53.3449, -6.2603), 50.));
+        assert!(moved_enough(dublin, (53.2707, -9.0568), 50.));
+    }
+
+    #[test]
+    fn test_describe_geo_error() {
+        assert_eq!(describe_geo_error(1), "permission was denied");
+        assert_eq!(describe_geo_error(3), "it took too long");
+        assert_eq!(describe_geo_error(0), "something went wrong");
+    }
+}
@@ -0,0 +1,165 @@
+use web_sys::{HtmlInputElement, HtmlTextAreaElement};
+use yew::{function_component, html, use_state, Callback, Event, Html, Properties, TargetCast};
+
+use crate::xml::locationforecast::client::{
+    format_headers, parse_headers, parse_max_attempts, parse_timeout, ForecastClientConfig,
+    ProxyMode, RequestPolicy, DEFAULT_BASE_URL,
+};
+
+#[derive(Properties, PartialEq)]
+pub struct ClientSettingsProps {
+    pub config: ForecastClientConfig,
+    /// Called with the whole config whenever any part of it changes
+    pub on_change: Callback<ForecastClientConfig>,
+}
+
+/// Collapsible panel for where the forecast is fetched from. Changes apply when a field loses
+/// focus.
+#[function_component(ClientSettings)]
+pub fn client_settings(props: &ClientSettingsProps) -> Html {
+    let header_error = use_state(|| None::<String>);
+    let policy_error = use_state(|| None::<String>);
+
+    let on_base_url = {
+        let config = props.config.clone();
+        let on_change = props.on_change.clone();
+        Callback::from(move |event: Event| {
+            let base_url = event.target_unchecked_into::<HtmlInputElement>().value();
+            let base_url = match base_url.trim() {
+                "" => DEFAULT_BASE_URL.to_string(),
+                base_url => base_url.to_string(),
+            };
+            on_change.emit(ForecastClientConfig {
+                base_url,
+                ..config.clone()
+            });
+        })
+    };
+    let on_proxy = {
+        let config = props.config.clone();
+        let on_change = props.on_change.clone();
+        Callback::from(move |event: Event| {
+            let proxy_url = event.target_unchecked_into::<HtmlInputElement>().value();
+            on_change.emit(ForecastClientConfig {
+                proxy: ProxyMode::from_url(&proxy_url),
+                ..config.clone()
+            });
+        })
+    };
+    let on_headers = {
+        let config = props.config.clone();
+        let on_change = props.on_change.clone();
+        let header_error = header_error.clone();
+        Callback::from(move |event: Event| {
+            let text = event.target_unchecked_into::<HtmlTextAreaElement>().value();
+            // Keep using the old headers until the new ones make sense
+            match parse_headers(&text) {
+                Ok(headers) => {
+                    header_error.set(None);
+                    on_change.emit(ForecastClientConfig {
+                        headers,
+                        ..config.clone()
+                    });
+                }
+                Err(err) => header_error.set(Some(err)),
+            }
+        })
+    };
+
+    let on_timeout = {
+        let config = props.config.clone();
+        let on_change = props.on_change.clone();
+        let policy_error = policy_error.clone();
+        Callback::from(move |event: Event| {
+            let text = event.target_unchecked_into::<HtmlInputElement>().value();
+            match parse_timeout(&text) {
+                Ok(timeout) => {
+                    policy_error.set(None);
+                    on_change.emit(ForecastClientConfig {
+                        policy: RequestPolicy {
+                            timeout,
+                            ..config.policy
+                        },
+                        ..config.clone()
+                    });
+                }
+                Err(err) => policy_error.set(Some(err)),
+            }
+        })
+    };
+    let on_max_attempts = {
+        let config = props.config.clone();
+        let on_change = props.on_change.clone();
+        let policy_error = policy_error.clone();
+        Callback::from(move |event: Event| {
+            let text = event.target_unchecked_into::<HtmlInputElement>().value();
+            match parse_max_attempts(&text) {
+                Ok(max_attempts) => {
+                    policy_error.set(None);
+                    on_change.emit(ForecastClientConfig {
+                        policy: RequestPolicy {
+                            max_attempts,
+                            ..config.policy
+                        },
+                        ..config.clone()
+                    });
+                }
+                Err(err) => policy_error.set(Some(err)),
+            }
+        })
+    };
+
+    html! {
+        <details class="client-settings">
+            <summary>{ "Forecast source" }</summary>
+            <label>
+                { "API base URL" }
+                <input
+                    placeholder={DEFAULT_BASE_URL}
+                    value={props.config.base_url.clone()}
+                    onchange={on_base_url}
+                />
+            </label>
+            <label>
+                { "CORS proxy" }
+                <input
+                    placeholder="None, requests go direct"
+                    value={props.config.proxy.url().to_string()}
+                    onchange={on_proxy}
+                />
+            </label>
+            <label>
+                { "Request headers" }
+                <textarea
+                    placeholder="Name: value, one per line"
+                    value={format_headers(&props.config.headers)}
+                    onchange={on_headers}
+                />
+            </label>
+            if let Some(err) = &*header_error {
+                <div class="error">{ err }</div>
+            }
+            <label>
+                { "Timeout per attempt (seconds)" }
+                <input
+                    type="number"
+                    min="1"
+                    value={props.config.policy.timeout.as_secs().to_string()}
+                    onchange={on_timeout}
+                />
+            </label>
+            <label>
+                { "Attempts before giving up" }
+                <input
+                    type="number"
+                    min="1"
+                    value={props.config.policy.max_attempts.to_string()}
+                    onchange={on_max_attempts}
+                />
+            </label>
+            if let Some(err) = &*policy_error {
+                <div class="error">{ err }</div>
+            }
+        </details>
+    }
+}
@@ -0,0 +1,52 @@
+use yew::{function_component, html, Html, Properties};
+
+use crate::xml::locationforecast::{
+    daily::DailySummary,
+    units::{Quantity, Units},
+};
+
+/// The card row covers the next week and a bit at most
+const MAX_DAYS: usize = 10;
+
+#[derive(Properties, PartialEq)]
+pub struct DailyCardsProps {
+    pub days: Vec<DailySummary>,
+    pub units: Units,
+}
+
+/// A row of cards, one per day, with the day's weather at a glance
+#[function_component(DailyCards)]
+pub fn daily_cards(props: &DailyCardsProps) -> Html {
+    let units = props.units;
+    let format = |quantity, value: Option<f32>| {
+        value
+            .map(|value| units.format(quantity, value))
+            .unwrap_or_else(|| "-".to_string())
+    };
+
+    html! {
+        <div class="daily-cards">
+        {
+            props.days.iter().take(MAX_DAYS).map(|day| html! {
+                <div class="daily-card">
+                    <div class="daily-date">{ day.date.format("%a %-d").to_string() }</div>
+                    if let Some(symbol) = day.symbol {
+                        <div class="symbol-icon" title={symbol.to_string()}>{ symbol.icon() }</div>
+                    }
+                    <div>
+                        { format(Quantity::Temperature, day.max_temperature) }
+                        { " / " }
+                        { format(Quantity::Temperature, day.min_temperature) }
+                    </div>
+                    <div>{ format(Quantity::Precipitation, Some(day.precipitation)) }</div>
+                    <div>
+                        { format(Quantity::Speed, day.max_gust) }
+                        { " " }
+                        { day.wind_direction.unwrap_or_default() }
+                    </div>
+                </div>
+            }).collect::<Html>()
+        }
+        </div>
+    }
+}
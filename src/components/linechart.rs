@@ -1,8 +1,17 @@
 use yew::{html, Component, Html, Properties};
 
-use crate::plot::bindings::create_chart_js;
+use crate::{
+    plot::{
+        bindings::create_chart_js,
+        resample::{resample_chart, Resample},
+    },
+    xml::locationforecast::symbol::SymbolSpan,
+};
 
-#[derive(Default, PartialEq, Clone)]
+/// The chart the weather symbols are drawn on, or the first one if it isn't there
+const SYMBOL_CHART_KEY: &str = "temperature";
+
+#[derive(Debug, Default, PartialEq, Clone)]
 pub struct LineChartData {
     pub key: String,
     pub title: Option<String>,
@@ -10,11 +19,22 @@ pub struct LineChartData {
     pub x_axis_title: Option<String>,
     pub x_data: Vec<String>,
     pub y_data: Vec<f32>,
+    /// IANA zone the `x_data` timestamps are in, if it isn't the browser's own
+    pub time_zone: Option<String>,
+    /// Which points were filled in by resampling rather than being in the forecast.
+    /// Empty if the series hasn't been resampled.
+    pub interpolated: Vec<bool>,
 }
 
 #[derive(Properties, PartialEq)]
 pub struct LineChartsListProps {
     pub chart_data: Vec<LineChartData>,
+    /// Shown as icons along the top of the temperature chart
+    #[prop_or_default]
+    pub symbols: Vec<SymbolSpan>,
+    /// Puts every chart onto the same time grid before plotting, if set
+    #[prop_or_default]
+    pub resample: Option<Resample>,
 }
 
 pub struct LineChartsList;
@@ -45,8 +65,26 @@ impl Component for LineChartsList {
 
     fn rendered(&mut self, ctx: &yew::Context<Self>, first_render: bool) {
         // Render the charts
-        ctx.props().chart_data.iter().for_each(|plot_data| {
-            create_chart_js(format!("chart-{}", plot_data.key).as_str(), plot_data);
+        let props = ctx.props();
+        let symbol_chart = props
+            .chart_data
+            .iter()
+            .find(|chart| chart.key == SYMBOL_CHART_KEY)
+            .or(props.chart_data.first())
+            .map(|chart| chart.key.as_str());
+        props.chart_data.iter().for_each(|plot_data| {
+            let id = format!("chart-{}", plot_data.key);
+            let symbols = if symbol_chart == Some(plot_data.key.as_str()) {
+                &props.symbols[..]
+            } else {
+                &[]
+            };
+            match &props.resample {
+                Some(resample) => {
+                    create_chart_js(&id, &resample_chart(plot_data, resample), symbols)
+                }
+                None => create_chart_js(&id, plot_data, symbols),
+            }
         });
     }
 }
@@ -1 +1,7 @@
+pub mod clientsettings;
+pub mod dailycards;
 pub mod linechart;
+pub mod resampleselect;
+pub mod skeleton;
+pub mod timezoneselect;
+pub mod unitsettings;
@@ -0,0 +1,50 @@
+use web_sys::HtmlSelectElement;
+use yew::{function_component, html, Callback, Event, Html, Properties, TargetCast};
+
+use crate::plot::resample::{Interpolation, Resample};
+
+/// The choices offered, all on an hourly grid
+const OPTIONS: &[(&str, &str, Option<Interpolation>)] = &[
+    ("raw", "Forecast times", None),
+    ("linear", "Hourly, linear", Some(Interpolation::Linear)),
+    ("step", "Hourly, step", Some(Interpolation::Step)),
+    ("nearest", "Hourly, nearest", Some(Interpolation::Nearest)),
+];
+
+#[derive(Properties, PartialEq)]
+pub struct ResampleSelectProps {
+    pub resample: Option<Resample>,
+    pub on_change: Callback<Option<Resample>>,
+}
+
+/// Dropdown for whether to put the charts on an even hourly grid, and how to fill the gaps
+#[function_component(ResampleSelect)]
+pub fn resample_select(props: &ResampleSelectProps) -> Html {
+    let selected = props.resample.map(|resample| resample.interpolation);
+
+    let onchange = {
+        let on_change = props.on_change.clone();
+        Callback::from(move |event: Event| {
+            let value = event.target_unchecked_into::<HtmlSelectElement>().value();
+            if let Some((_, _, interpolation)) = OPTIONS.iter().find(|(name, _, _)| *name == value)
+            {
+                on_change.emit(interpolation.map(|interpolation| Resample {
+                    interpolation,
+                    ..Default::default()
+                }));
+            }
+        })
+    };
+
+    html! {
+        <div class="resample-select">
+            <select {onchange}>
+            {
+                OPTIONS.iter().map(|(name, label, interpolation)| html! {
+                    <option value={*name} selected={*interpolation == selected}>{ *label }</option>
+                }).collect::<Html>()
+            }
+            </select>
+        </div>
+    }
+}
@@ -0,0 +1,21 @@
+use yew::{function_component, html, Html, Properties};
+
+#[derive(Properties, PartialEq)]
+pub struct ChartSkeletonsProps {
+    /// How many placeholder charts to show
+    pub count: usize,
+}
+
+/// Grey placeholders the size of the charts, shown while the forecast loads
+#[function_component(ChartSkeletons)]
+pub fn chart_skeletons(props: &ChartSkeletonsProps) -> Html {
+    html! {
+        <div class="skeletons" aria-busy="true">
+        {
+            (0..props.count).map(|_| html! {
+                <div class="chart-container skeleton"></div>
+            }).collect::<Html>()
+        }
+        </div>
+    }
+}
@@ -0,0 +1,43 @@
+use web_sys::{console, HtmlInputElement};
+use yew::{function_component, html, Callback, Event, Html, Properties, TargetCast};
+
+use crate::xml::locationforecast::timezone::DisplayZone;
+
+#[derive(Properties, PartialEq)]
+pub struct TimezoneSelectProps {
+    pub timezone: DisplayZone,
+    pub on_change: Callback<DisplayZone>,
+}
+
+/// Text box for an IANA zone name, with suggestions. Left empty it uses the browser's zone.
+#[function_component(TimezoneSelect)]
+pub fn timezone_select(props: &TimezoneSelectProps) -> Html {
+    let onchange = {
+        let on_change = props.on_change.clone();
+        Callback::from(move |event: Event| {
+            let name = event.target_unchecked_into::<HtmlInputElement>().value();
+            match DisplayZone::parse(&name) {
+                Ok(timezone) => on_change.emit(timezone),
+                Err(err) => console::warn_1(&err.into()),
+            }
+        })
+    };
+
+    html! {
+        <div class="timezone-select">
+            <input
+                list="timezones"
+                placeholder="Browser timezone"
+                value={props.timezone.name().unwrap_or_default()}
+                {onchange}
+            />
+            <datalist id="timezones">
+            {
+                chrono_tz::TZ_VARIANTS.iter().map(|tz| html! {
+                    <option value={tz.name()} />
+                }).collect::<Html>()
+            }
+            </datalist>
+        </div>
+    }
+}
@@ -0,0 +1,79 @@
+use web_sys::HtmlSelectElement;
+use yew::{function_component, html, Callback, Event, Html, Properties, TargetCast};
+
+use crate::xml::locationforecast::units::{UnitOption, UnitPreset, Units};
+
+#[derive(Properties, PartialEq)]
+pub struct UnitSettingsProps {
+    pub units: Units,
+    /// Called with the whole set of units whenever any of them change
+    pub on_change: Callback<Units>,
+}
+
+/// Reads the selected option's value when a `<select>` changes
+fn select_value(event: Event) -> String {
+    event.target_unchecked_into::<HtmlSelectElement>().value()
+}
+
+/// A dropdown with every option for one kind of unit
+fn unit_select<T: UnitOption>(selected: T, onchange: Callback<Event>) -> Html {
+    html! {
+        <select {onchange}>
+        {
+            T::ALL.iter().map(|unit| html! {
+                <option value={unit.name()} selected={*unit == selected}>{ unit.label() }</option>
+            }).collect::<Html>()
+        }
+        </select>
+    }
+}
+
+#[function_component(UnitSettings)]
+pub fn unit_settings(props: &UnitSettingsProps) -> Html {
+    let units = props.units;
+
+    let on_preset = {
+        let on_change = props.on_change.clone();
+        Callback::from(move |event: Event| match select_value(event).as_str() {
+            "metric" => on_change.emit(Units::metric()),
+            "imperial" => on_change.emit(Units::imperial()),
+            _ => {}
+        })
+    };
+    // Each dropdown swaps out its own field and keeps the rest
+    let on_field = |update: fn(Units, &str) -> Option<Units>| {
+        let on_change = props.on_change.clone();
+        Callback::from(move |event: Event| {
+            if let Some(units) = update(units, &select_value(event)) {
+                on_change.emit(units);
+            }
+        })
+    };
+    let preset = units.preset();
+
+    html! {
+        <div class="unit-settings">
+            <select onchange={on_preset}>
+                <option value="metric" selected={preset == UnitPreset::Metric}>{ "Metric" }</option>
+                <option value="imperial" selected={preset == UnitPreset::Imperial}>{ "Imperial" }</option>
+                <option value="custom" selected={preset == UnitPreset::Custom} disabled=true>{ "Custom" }</option>
+            </select>
+            { unit_select(units.temperature, on_field(|units, name| Some(Units {
+                temperature: name.parse().ok()?,
+                ..units
+            }))) }
+            { unit_select(units.speed, on_field(|units, name| Some(Units {
+                speed: name.parse().ok()?,
+                ..units
+            }))) }
+            { unit_select(units.precipitation, on_field(|units, name| Some(Units {
+                precipitation: name.parse().ok()?,
+                ..units
+            }))) }
+            { unit_select(units.pressure, on_field(|units, name| Some(Units {
+                pressure: name.parse().ok()?,
+                ..units
+            }))) }
+        </div>
+    }
+}
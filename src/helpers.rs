@@ -11,9 +11,22 @@ extern "C" {
     #[wasm_bindgen(method, getter)]
     pub fn longitude(this: &GeolocationCoordinates) -> f64;
 
+    /// Radius of the 95% confidence circle, in metres
+    #[wasm_bindgen(method, getter)]
+    pub fn accuracy(this: &GeolocationCoordinates) -> f64;
+
     pub type GeolocationPosition;
 
     #[wasm_bindgen(method, getter)]
     pub fn coords(this: &GeolocationPosition) -> GeolocationCoordinates;
+
+    pub type GeolocationPositionError;
+
+    /// 1 for permission denied, 2 for position unavailable, 3 for timeout
+    #[wasm_bindgen(method, getter)]
+    pub fn code(this: &GeolocationPositionError) -> u16;
+
+    #[wasm_bindgen(method, getter)]
+    pub fn message(this: &GeolocationPositionError) -> String;
 }
 
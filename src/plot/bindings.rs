@@ -1,4 +1,6 @@
-use crate::components::linechart::LineChartData;
+use crate::{components::linechart::LineChartData, xml::locationforecast::symbol::SymbolSpan};
+use chrono::SecondsFormat;
+use serde::Serialize;
 use wasm_bindgen::prelude::*;
 
 // Bind to the JavaScript function for creating the chart
@@ -10,14 +12,42 @@ extern "C" {
         data: JsValue,
         title: JsValue,
         y_label: JsValue,
+        time_zone: JsValue,
+        interpolated: JsValue,
+        symbols: JsValue,
     );
 }
 
-pub fn create_chart_js(parent_element_id: &str, data: &LineChartData) {
+/// What `create_chart` needs to draw a weather symbol
+#[derive(Serialize)]
+struct ChartSymbol {
+    from: String,
+    to: String,
+    icon: &'static str,
+    /// For the tooltip, eg. "Tue 13:00: Light rain"
+    description: String,
+}
+
+impl From<&SymbolSpan> for ChartSymbol {
+    fn from(span: &SymbolSpan) -> Self {
+        Self {
+            from: span.from.to_rfc3339_opts(SecondsFormat::Secs, true),
+            to: span.to.to_rfc3339_opts(SecondsFormat::Secs, true),
+            icon: span.symbol.icon(),
+            description: format!("{}: {}", span.label, span.symbol),
+        }
+    }
+}
+
+pub fn create_chart_js(parent_element_id: &str, data: &LineChartData, symbols: &[SymbolSpan]) {
     let labels = serde_wasm_bindgen::to_value(&data.x_data).unwrap();
     let y_data = serde_wasm_bindgen::to_value(&data.y_data).unwrap();
     let title = data.title.clone().unwrap_or("".to_string());
     let y_label = data.y_axis_title.clone().unwrap_or("".to_string());
+    let time_zone = data.time_zone.clone().unwrap_or("".to_string());
+    let interpolated = serde_wasm_bindgen::to_value(&data.interpolated).unwrap();
+    let symbols = symbols.iter().map(ChartSymbol::from).collect::<Vec<_>>();
+    let symbols = serde_wasm_bindgen::to_value(&symbols).unwrap();
 
     create_chart(
         parent_element_id.into(),
@@ -25,5 +55,8 @@ pub fn create_chart_js(parent_element_id: &str, data: &LineChartData) {
         y_data,
         title.into(),
         y_label.into(),
+        time_zone.into(),
+        interpolated,
+        symbols,
     );
 }
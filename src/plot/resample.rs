@@ -0,0 +1,254 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::{components::linechart::LineChartData, xml::locationforecast::timezone::DisplayZone};
+
+/// How to fill in values between the ones we have
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Straight line between the neighbouring values
+    Linear,
+    /// Hold the previous value until the next one
+    Step,
+    /// Whichever neighbouring value is closer in time, the earlier one if it's a tie
+    Nearest,
+}
+
+/// A uniform time grid to put a series onto
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Resample {
+    pub step: Duration,
+    pub interpolation: Interpolation,
+}
+
+impl Default for Resample {
+    fn default() -> Self {
+        Self {
+            step: Duration::hours(1),
+            interpolation: Interpolation::Linear,
+        }
+    }
+}
+
+/// One point on the grid
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub time: DateTime<Utc>,
+    pub value: f32,
+    /// Whether the value was filled in rather than being in the original series
+    pub interpolated: bool,
+}
+
+/// Puts a series onto a grid starting at its first point and going up to its last, one `step`
+/// apart. Points which land exactly on an original time keep the original value.
+pub fn resample(points: &[(DateTime<Utc>, f32)], options: &Resample) -> Vec<Sample> {
+    let mut points = points.to_vec();
+    points.sort_by_key(|(time, _)| *time);
+    points.dedup_by_key(|(time, _)| *time);
+
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return vec![];
+    };
+    // Nothing sensible to do with a zero or negative step, so leave the series as it is
+    if options.step <= Duration::zero() {
+        return points
+            .iter()
+            .map(|(time, value)| Sample {
+                time: *time,
+                value: *value,
+                interpolated: false,
+            })
+            .collect();
+    }
+
+    let mut samples = vec![];
+    // Index of the last original point at or before the current grid time
+    let mut before = 0;
+    let mut time = first.0;
+    while time <= last.0 {
+        while before + 1 < points.len() && points[before + 1].0 <= time {
+            before += 1;
+        }
+        let (before_time, before_value) = points[before];
+
+        let sample = if before_time == time || before + 1 == points.len() {
+            Sample {
+                time,
+                value: before_value,
+                interpolated: before_time != time,
+            }
+        } else {
+            let (after_time, after_value) = points[before + 1];
+            let fraction = (time - before_time).num_milliseconds() as f32
+                / (after_time - before_time).num_milliseconds() as f32;
+            let value = match options.interpolation {
+                Interpolation::Linear => before_value + (after_value - before_value) * fraction,
+                Interpolation::Step => before_value,
+                Interpolation::Nearest if fraction <= 0.5 => before_value,
+                Interpolation::Nearest => after_value,
+            };
+            Sample {
+                time,
+                value,
+                interpolated: true,
+            }
+        };
+        samples.push(sample);
+        time += options.step;
+    }
+
+    samples
+}
+
+/// Resamples a chart whose `x_data` are ISO-8601 timestamps, relabelling the new points in the
+/// chart's own zone. Charts with labels that aren't timestamps are returned as they are.
+pub fn resample_chart(chart: &LineChartData, options: &Resample) -> LineChartData {
+    let times = chart
+        .x_data
+        .iter()
+        .map(|label| DateTime::parse_from_rfc3339(label).map(|time| time.to_utc()))
+        .collect::<Result<Vec<_>, _>>();
+    let Ok(times) = times else {
+        return chart.clone();
+    };
+    let points = times
+        .into_iter()
+        .zip(chart.y_data.iter().copied())
+        .collect::<Vec<_>>();
+
+    let timezone = chart
+        .time_zone
+        .as_deref()
+        .and_then(|name| DisplayZone::parse(name).ok())
+        .unwrap_or_default();
+    let samples = resample(&points, options);
+
+    LineChartData {
+        x_data: samples
+            .iter()
+            .map(|sample| timezone.iso_label(&sample.time))
+            .collect(),
+        y_data: samples.iter().map(|sample| sample.value).collect(),
+        interpolated: samples.iter().map(|sample| sample.interpolated).collect(),
+        ..chart.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn hour(hour: i64) -> DateTime<Utc> {
+        "2025-01-14T00:00:00Z".parse::<DateTime<Utc>>().unwrap() + Duration::hours(hour)
+    }
+
+    /// Hourly, then 3-hourly, then 6-hourly like the real forecast
+    fn points() -> Vec<(DateTime<Utc>, f32)> {
+        vec![(hour(0), 0.), (hour(1), 1.), (hour(4), 4.), (hour(10), -2.)]
+    }
+
+    fn resampled(interpolation: Interpolation) -> Vec<(f32, bool)> {
+        let options = Resample {
+            interpolation,
+            ..Default::default()
+        };
+        resample(&points(), &options)
+            .into_iter()
+            .map(|sample| (sample.value, sample.interpolated))
+            .collect()
+    }
+
+    #[test]
+    fn test_linear() {
+        let samples = resampled(Interpolation::Linear);
+
+        assert_eq!(samples.len(), 11);
+        assert_eq!(
+            samples[..5],
+            [
+                (0., false),
+                (1., false),
+                (2., true),
+                (3., true),
+                (4., false)
+            ]
+        );
+        assert_eq!(samples[7], (1., true));
+        assert_eq!(samples[10], (-2., false));
+    }
+
+    #[test]
+    fn test_step() {
+        let samples = resampled(Interpolation::Step);
+
+        assert_eq!(samples[2], (1., true));
+        assert_eq!(samples[3], (1., true));
+        assert_eq!(samples[9], (4., true));
+        assert_eq!(samples[10], (-2., false));
+    }
+
+    #[test]
+    fn test_nearest() {
+        let samples = resampled(Interpolation::Nearest);
+
+        assert_eq!(samples[2], (1., true));
+        assert_eq!(samples[3], (4., true));
+        // Exactly halfway goes to the earlier value
+        assert_eq!(samples[7], (4., true));
+        assert_eq!(samples[8], (-2., true));
+    }
+
+    #[test]
+    fn test_resample_edge_cases() {
+        assert!(resample(&[], &Resample::default()).is_empty());
+
+        // Unsorted and duplicated input is tidied up first
+        let samples = resample(
+            &[(hour(2), 2.), (hour(0), 0.), (hour(2), 5.)],
+            &Resample::default(),
+        );
+        let values = samples.iter().map(|x| x.value).collect::<Vec<_>>();
+        assert_eq!(values, vec![0., 1., 2.]);
+
+        let zero_step = Resample {
+            step: Duration::zero(),
+            ..Default::default()
+        };
+        assert_eq!(resample(&points(), &zero_step).len(), 4);
+    }
+
+    #[test]
+    fn test_resample_chart() {
+        let chart = LineChartData {
+            key: "temperature".to_string(),
+            x_data: vec![
+                "2025-03-30T00:00:00+00:00".to_string(),
+                "2025-03-30T04:00:00+01:00".to_string(),
+            ],
+            y_data: vec![0., 3.],
+            time_zone: Some("Europe/Dublin".to_string()),
+            ..Default::default()
+        };
+        let resampled = resample_chart(&chart, &Resample::default());
+
+        // Three hours apart in real time, across the clocks going forward
+        assert_eq!(
+            resampled.x_data,
+            vec![
+                "2025-03-30T00:00:00+00:00",
+                "2025-03-30T02:00:00+01:00",
+                "2025-03-30T03:00:00+01:00",
+                "2025-03-30T04:00:00+01:00"
+            ]
+        );
+        assert_eq!(resampled.y_data, vec![0., 1., 2., 3.]);
+        assert_eq!(resampled.interpolated, vec![false, true, true, false]);
+        assert_eq!(resampled.key, "temperature");
+
+        let not_times = LineChartData {
+            x_data: vec!["Monday".to_string()],
+            y_data: vec![1.],
+            ..Default::default()
+        };
+        assert_eq!(resample_chart(&not_times, &Resample::default()), not_times);
+    }
+}
@@ -1,75 +1,608 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt};
 
 use quick_xml::{
     events::{BytesStart, Event},
-    reader::Reader,
+    name::ResolveResult,
+    reader::NsReader,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct XMLItem {
     pub name: String,
+    /// Resolved namespace URI, only filled in when parsing with `resolve_namespaces`
+    pub namespace: Option<String>,
+    /// Keyed by local name. When resolving namespaces, attributes bound to a namespace are
+    /// keyed as `{uri}local` instead so they can't collide with unqualified ones.
     pub attributes: HashMap<String, String>,
-    pub children: Vec<XMLItem>,
-}
-
-fn parse_tag(bytes_start: BytesStart) -> XMLItem {
-    let name = String::from_utf8(bytes_start.local_name().as_ref().to_vec())
-        .expect("Failed to parse tag name");
-    let attributes = bytes_start
-        .attributes()
-        .map(|attribute| {
-            let attribute = attribute.expect("Failed to read attribute");
-            (
-                String::from_utf8(attribute.key.local_name().as_ref().to_vec())
-                    .expect("Failed to parse attribute name"),
-                attribute
-                    .unescape_value()
-                    .expect("Failed to parse attribute value")
-                    .to_string(),
-            )
+    /// Child elements and text, in document order
+    pub nodes: Vec<XMLNode>,
+}
+
+/// A single piece of an element's content
+#[derive(Debug, Clone, PartialEq)]
+pub enum XMLNode {
+    Element(XMLItem),
+    Text(String),
+    CData(String),
+}
+
+impl XMLItem {
+    /// Iterates over the child elements, skipping any text
+    pub fn children(&self) -> impl Iterator<Item = &XMLItem> {
+        self.nodes.iter().filter_map(|node| match node {
+            XMLNode::Element(item) => Some(item),
+            _ => None,
         })
-        .collect::<HashMap<_, _>>();
+    }
+
+    /// Concatenates the text and CDATA directly inside this element
+    #[cfg(test)]
+    pub fn text(&self) -> String {
+        self.nodes
+            .iter()
+            .filter_map(|node| match node {
+                XMLNode::Text(text) | XMLNode::CData(text) => Some(text.as_str()),
+                XMLNode::Element(_) => None,
+            })
+            .collect()
+    }
+
+    /// Looks up an attribute bound to a namespace, see `ParseOptions::resolve_namespaces`
+    #[cfg(test)]
+    pub fn attr_ns(&self, namespace: &str, local_name: &str) -> Option<&str> {
+        self.attributes
+            .get(&namespaced_key(namespace, local_name))
+            .map(String::as_str)
+    }
+}
+
+/// Key used in `XMLItem::attributes` for an attribute bound to a namespace
+pub fn namespaced_key(namespace: &str, local_name: &str) -> String {
+    format!("{{{}}}{}", namespace, local_name)
+}
+
+/// Splits a `namespaced_key` back into its namespace and local name
+pub fn split_namespaced_key(key: &str) -> Option<(&str, &str)> {
+    key.strip_prefix('{')?.split_once('}')
+}
+
+/// Controls how `parse_xml_with` treats text content and namespaces
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// Strip leading/trailing whitespace from text, dropping whitespace-only text entirely
+    pub trim_text: bool,
+    /// Replace entity references like `&amp;` in text. CDATA is never unescaped.
+    pub unescape_text: bool,
+    /// Record the namespace URI of each element and attribute rather than just stripping
+    /// prefixes. `xmlns` declarations are consumed and not kept as attributes.
+    pub resolve_namespaces: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            trim_text: true,
+            unescape_text: true,
+            resolve_namespaces: false,
+        }
+    }
+}
+
+/// Where in the source document an error was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    /// Byte offset from the start of the document
+    pub offset: u64,
+    /// 1-based line number
+    pub line: usize,
+}
+
+impl Position {
+    /// Works out the line number for a byte offset reported by quick-xml
+    pub(crate) fn in_document(data: &str, offset: u64) -> Self {
+        let end = (offset as usize).min(data.len());
+        let line = data.as_bytes()[..end]
+            .iter()
+            .filter(|&&byte| byte == b'\n')
+            .count()
+            + 1;
+
+        Self { offset, line }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {} (byte {})", self.line, self.offset)
+    }
+}
+
+/// Everything that can go wrong when parsing a document into an `XMLItem` tree
+#[derive(Debug, Clone, PartialEq)]
+pub enum XmlError {
+    /// The markup is broken, eg. an unclosed tag or a truncated document
+    Malformed { message: String, position: Position },
+    /// An end tag doesn't close the element that is currently open
+    MismatchedEndTag {
+        expected: Option<String>,
+        found: String,
+        position: Position,
+    },
+    /// A name, attribute or text node isn't valid in the document's encoding
+    InvalidEncoding { message: String, position: Position },
+    /// There is no root element at all
+    EmptyDocument,
+}
+
+impl XmlError {
+    pub(crate) fn from_quick_xml(err: quick_xml::Error, position: Position) -> Self {
+        use quick_xml::errors::IllFormedError;
 
-    XMLItem {
+        match err {
+            quick_xml::Error::IllFormed(IllFormedError::MismatchedEndTag { expected, found }) => {
+                Self::MismatchedEndTag {
+                    expected: Some(expected),
+                    found,
+                    position,
+                }
+            }
+            quick_xml::Error::IllFormed(IllFormedError::UnmatchedEndTag(found)) => {
+                Self::MismatchedEndTag {
+                    expected: None,
+                    found,
+                    position,
+                }
+            }
+            quick_xml::Error::Encoding(err) => Self::InvalidEncoding {
+                message: err.to_string(),
+                position,
+            },
+            err => Self::Malformed {
+                message: err.to_string(),
+                position,
+            },
+        }
+    }
+}
+
+impl fmt::Display for XmlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed { message, position } => {
+                write!(f, "Malformed XML at {}: {}", position, message)
+            }
+            Self::MismatchedEndTag {
+                expected: Some(expected),
+                found,
+                position,
+            } => write!(
+                f,
+                "Mismatched end tag at {}: expected </{}>, found </{}>",
+                position, expected, found
+            ),
+            Self::MismatchedEndTag {
+                expected: None,
+                found,
+                position,
+            } => write!(
+                f,
+                "Unexpected end tag at {}: </{}> has no matching start tag",
+                position, found
+            ),
+            Self::InvalidEncoding { message, position } => {
+                write!(f, "Invalid encoding at {}: {}", position, message)
+            }
+            Self::EmptyDocument => write!(f, "XML document has no root element"),
+        }
+    }
+}
+
+impl std::error::Error for XmlError {}
+
+fn decode_name(bytes: &[u8], position: Position) -> Result<String, XmlError> {
+    String::from_utf8(bytes.to_vec()).map_err(|err| XmlError::InvalidEncoding {
+        message: err.to_string(),
+        position,
+    })
+}
+
+/// Turns a namespace lookup into the URI, failing on prefixes that were never declared
+fn resolved_namespace(
+    resolved: ResolveResult,
+    position: Position,
+) -> Result<Option<String>, XmlError> {
+    match resolved {
+        ResolveResult::Bound(namespace) => decode_name(namespace.as_ref(), position).map(Some),
+        ResolveResult::Unbound => Ok(None),
+        ResolveResult::Unknown(prefix) => Err(XmlError::Malformed {
+            message: format!(
+                "Undeclared namespace prefix '{}'",
+                String::from_utf8_lossy(&prefix)
+            ),
+            position,
+        }),
+    }
+}
+
+/// Builds an element from a start tag. When `resolver` is given, names are resolved
+/// against the namespace declarations currently in scope.
+pub(crate) fn parse_tag(
+    bytes_start: BytesStart,
+    position: Position,
+    resolver: Option<&NsReader<&[u8]>>,
+) -> Result<XMLItem, XmlError> {
+    let name = decode_name(bytes_start.local_name().as_ref(), position)?;
+    let namespace = match resolver {
+        Some(reader) => resolved_namespace(reader.resolve_element(bytes_start.name()).0, position)?,
+        None => None,
+    };
+
+    let mut attributes = HashMap::new();
+    for attribute in bytes_start.attributes() {
+        let attribute = attribute.map_err(|err| XmlError::from_quick_xml(err.into(), position))?;
+        let local_name = decode_name(attribute.key.local_name().as_ref(), position)?;
+        let key = match resolver {
+            // Declarations are already captured in the resolved namespaces
+            Some(_) if attribute.key.as_namespace_binding().is_some() => continue,
+            Some(reader) => {
+                match resolved_namespace(reader.resolve_attribute(attribute.key).0, position)? {
+                    Some(namespace) => namespaced_key(&namespace, &local_name),
+                    None => local_name,
+                }
+            }
+            None => local_name,
+        };
+        let value = attribute
+            .unescape_value()
+            .map_err(|err| XmlError::from_quick_xml(err, position))?
+            .to_string();
+
+        attributes.insert(key, value);
+    }
+
+    Ok(XMLItem {
         name,
+        namespace,
         attributes,
-        children: vec![],
+        nodes: vec![],
+    })
+}
+
+/// Appends text to the currently open element, merging it with any text just before it.
+/// Trimming waits until the run of text is finished, see `finish_text`.
+fn push_text(stack: &mut [XMLItem], text: String, options: &ParseOptions, cdata: bool) {
+    // Text outside the root element is just whitespace between declarations
+    let Some(parent) = stack.last_mut() else {
+        return;
+    };
+
+    if cdata {
+        finish_text(parent, options);
+    }
+    if text.is_empty() {
+        return;
+    }
+
+    match (parent.nodes.last_mut(), cdata) {
+        (Some(XMLNode::Text(previous)), false) => previous.push_str(&text),
+        (_, false) => parent.nodes.push(XMLNode::Text(text)),
+        (_, true) => parent.nodes.push(XMLNode::CData(text)),
+    }
+}
+
+/// Trims the run of text at the end of an element, once something other than text comes
+/// along. Trimming fragments separately would lose the spaces between words either side of
+/// a comment or entity.
+pub(super) fn finish_text(item: &mut XMLItem, options: &ParseOptions) {
+    if !options.trim_text {
+        return;
+    }
+    if let Some(XMLNode::Text(text)) = item.nodes.last_mut() {
+        let trimmed = text.trim();
+        if trimmed.is_empty() {
+            item.nodes.pop();
+        } else if trimmed.len() != text.len() {
+            *text = trimmed.to_string();
+        }
+    }
+}
+
+/// Adds a finished element to its parent, or makes it the root if there's no parent
+fn attach(
+    stack: &mut [XMLItem],
+    root: &mut Option<XMLItem>,
+    item: XMLItem,
+    position: Position,
+) -> Result<(), XmlError> {
+    match stack.last_mut() {
+        Some(parent) => parent.nodes.push(XMLNode::Element(item)),
+        None if root.is_none() => *root = Some(item),
+        None => {
+            return Err(XmlError::Malformed {
+                message: format!("Found a second root element <{}>", item.name),
+                position,
+            })
+        }
     }
+
+    Ok(())
 }
 
 /// Recursively parses an XML structure
-pub fn parse_xml(data: &str) -> XMLItem {
-    let mut reader = Reader::from_str(data);
+pub fn parse_xml(data: &str) -> Result<XMLItem, XmlError> {
+    parse_xml_with(data, &ParseOptions::default())
+}
+
+/// Recursively parses an XML structure, with control over how text and namespaces are handled
+pub fn parse_xml_with(data: &str, options: &ParseOptions) -> Result<XMLItem, XmlError> {
+    let mut reader = NsReader::from_str(data);
     let mut stack = vec![];
+    let mut root = None;
 
     loop {
-        match reader.read_event().unwrap() {
+        let event = reader.read_event().map_err(|err| {
+            XmlError::from_quick_xml(err, Position::in_document(data, reader.error_position()))
+        })?;
+        let position = Position::in_document(data, reader.buffer_position());
+
+        match event {
             // Start tag contains attributes and has children
             Event::Start(bytes_start) => {
-                let tag = parse_tag(bytes_start);
+                let tag = parse_tag(
+                    bytes_start,
+                    position,
+                    options.resolve_namespaces.then_some(&reader),
+                )?;
+                if let Some(parent) = stack.last_mut() {
+                    finish_text(parent, options);
+                }
                 stack.push(tag);
             }
             // End tag contains no attributes
-            Event::End(_) => {
-                let child = stack.pop().expect("Found end tag with no matching start!");
-                if let Some(parent) = stack.last_mut() {
-                    parent.children.push(child);
-                } else {
-                    stack.push(child);
-                }
+            Event::End(bytes_end) => {
+                let mut child = stack.pop().ok_or_else(|| XmlError::MismatchedEndTag {
+                    expected: None,
+                    found: String::from_utf8_lossy(bytes_end.name().as_ref()).to_string(),
+                    position,
+                })?;
+                finish_text(&mut child, options);
+                attach(&mut stack, &mut root, child, position)?;
             }
             // Empty tag has attributes but no children
             Event::Empty(bytes_start) => {
-                let tag = parse_tag(bytes_start);
+                let tag = parse_tag(
+                    bytes_start,
+                    position,
+                    options.resolve_namespaces.then_some(&reader),
+                )?;
                 if let Some(parent) = stack.last_mut() {
-                    parent.children.push(tag);
+                    finish_text(parent, options);
+                }
+                attach(&mut stack, &mut root, tag, position)?;
+            }
+            Event::Text(bytes_text) => {
+                let text = if options.unescape_text {
+                    bytes_text.unescape()
+                } else {
+                    reader.decoder().decode(&bytes_text).map_err(Into::into)
                 }
+                .map_err(|err| XmlError::from_quick_xml(err, position))?;
+                push_text(&mut stack, text.to_string(), options, false);
+            }
+            Event::CData(bytes_cdata) => {
+                let text = reader
+                    .decoder()
+                    .decode(&bytes_cdata)
+                    .map_err(|err| XmlError::from_quick_xml(err.into(), position))?;
+                push_text(&mut stack, text.to_string(), options, true);
             }
             Event::Eof => break,
             _ => {}
         }
     }
 
-    assert_eq!(stack.len(), 1, "Only root node should be left.");
-    stack.pop().unwrap()
+    // Anything still open means the document was cut off part way through
+    if let Some(unclosed) = stack.last() {
+        return Err(XmlError::Malformed {
+            message: format!("Document ended before <{}> was closed", unclosed.name),
+            position: Position::in_document(data, reader.buffer_position()),
+        });
+    }
+
+    root.ok_or(XmlError::EmptyDocument)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_xml_nested() {
+        let root = parse_xml(r#"<a x="1"><b y="2"/><c></c></a>"#).unwrap();
+
+        assert_eq!(root.name, "a");
+        assert_eq!(root.attributes.get("x").map(String::as_str), Some("1"));
+        let children = root.children().collect::<Vec<_>>();
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[0].name, "b");
+        assert_eq!(children[1].name, "c");
+    }
+
+    #[test]
+    fn test_parse_xml_text() {
+        let root = parse_xml("<a>\n  Fish &amp; chips\n</a>").unwrap();
+        assert_eq!(root.nodes, vec![XMLNode::Text("Fish & chips".to_string())]);
+        assert_eq!(root.text(), "Fish & chips");
+    }
+
+    #[test]
+    fn test_parse_xml_cdata() {
+        let root = parse_xml("<a><![CDATA[<b>&amp;</b>]]></a>").unwrap();
+        assert_eq!(root.nodes, vec![XMLNode::CData("<b>&amp;</b>".to_string())]);
+        assert_eq!(root.children().count(), 0);
+    }
+
+    #[test]
+    fn test_parse_xml_mixed_content() {
+        let root = parse_xml("<p>Heavy <b>rain</b> expected <!-- note -->tonight</p>").unwrap();
+        assert_eq!(
+            root.nodes,
+            vec![
+                XMLNode::Text("Heavy".to_string()),
+                XMLNode::Element(XMLItem {
+                    name: "b".to_string(),
+                    namespace: None,
+                    attributes: HashMap::new(),
+                    nodes: vec![XMLNode::Text("rain".to_string())],
+                }),
+                XMLNode::Text("expected tonight".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_xml_options() {
+        let options = ParseOptions {
+            trim_text: false,
+            unescape_text: false,
+            ..Default::default()
+        };
+        let root = parse_xml_with("<a> x &lt; y <b/> </a>", &options).unwrap();
+        assert_eq!(
+            root.nodes,
+            vec![
+                XMLNode::Text(" x &lt; y ".to_string()),
+                XMLNode::Element(XMLItem {
+                    name: "b".to_string(),
+                    namespace: None,
+                    attributes: HashMap::new(),
+                    nodes: vec![],
+                }),
+                XMLNode::Text(" ".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_xml_empty_root() {
+        let root = parse_xml(r#"<?xml version="1.0"?><a/>"#).unwrap();
+        assert_eq!(root.name, "a");
+    }
+
+    #[test]
+    fn test_parse_xml_empty_document() {
+        assert_eq!(parse_xml("").unwrap_err(), XmlError::EmptyDocument);
+        assert_eq!(
+            parse_xml("<?xml version=\"1.0\"?>\n").unwrap_err(),
+            XmlError::EmptyDocument
+        );
+    }
+
+    #[test]
+    fn test_parse_xml_truncated() {
+        let err = parse_xml("<a>\n<b>\n<c/>").unwrap_err();
+        assert!(matches!(
+            err,
+            XmlError::Malformed {
+                position: Position { line: 3, .. },
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_xml_mismatched_end_tag() {
+        let err = parse_xml("<a>\n<b></c>\n</a>").unwrap_err();
+        match err {
+            XmlError::MismatchedEndTag {
+                expected,
+                found,
+                position,
+            } => {
+                assert_eq!(expected.as_deref(), Some("b"));
+                assert_eq!(found, "c");
+                assert_eq!(position.line, 2);
+            }
+            _ => panic!("Wrong error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_parse_xml_unmatched_end_tag() {
+        let err = parse_xml("<a></a></b>").unwrap_err();
+        assert!(matches!(
+            err,
+            XmlError::MismatchedEndTag { expected: None, .. }
+        ));
+    }
+
+    #[test]
+    fn test_parse_xml_bad_attribute() {
+        let err = parse_xml(r#"<a x="&bogus;"/>"#).unwrap_err();
+        assert!(matches!(err, XmlError::Malformed { .. }));
+    }
+
+    #[test]
+    fn test_parse_xml_multiple_roots() {
+        let err = parse_xml("<a/><b/>").unwrap_err();
+        assert!(matches!(err, XmlError::Malformed { .. }));
+    }
+
+    #[test]
+    fn test_parse_xml_namespaces() {
+        let data = r#"<weatherdata xmlns="urn:weather"
+            xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"
+            xsi:noNamespaceSchemaLocation="a.xsd" noNamespaceSchemaLocation="b.xsd">
+            <gml:point xmlns:gml="http://www.opengis.net/gml" gml:id="p1" id="p2"/>
+        </weatherdata>"#;
+        let options = ParseOptions {
+            resolve_namespaces: true,
+            ..Default::default()
+        };
+        let root = parse_xml_with(data, &options).unwrap();
+
+        assert_eq!(root.namespace.as_deref(), Some("urn:weather"));
+        assert_eq!(root.attributes.len(), 2);
+        assert_eq!(
+            root.attr_ns(
+                "http://www.w3.org/2001/XMLSchema-instance",
+                "noNamespaceSchemaLocation"
+            ),
+            Some("a.xsd")
+        );
+        assert_eq!(
+            root.attributes
+                .get("noNamespaceSchemaLocation")
+                .map(String::as_str),
+            Some("b.xsd")
+        );
+
+        let point = root.children().next().unwrap();
+        assert_eq!(point.name, "point");
+        assert_eq!(
+            point.namespace.as_deref(),
+            Some("http://www.opengis.net/gml")
+        );
+        assert_eq!(
+            point.attr_ns("http://www.opengis.net/gml", "id"),
+            Some("p1")
+        );
+        assert_eq!(point.attributes.get("id").map(String::as_str), Some("p2"));
+    }
+
+    #[test]
+    fn test_parse_xml_namespaces_off_by_default() {
+        let root = parse_xml(r#"<a xmlns="urn:a"/>"#).unwrap();
+        assert_eq!(root.namespace, None);
+    }
+
+    #[test]
+    fn test_parse_xml_undeclared_prefix() {
+        let options = ParseOptions {
+            resolve_namespaces: true,
+            ..Default::default()
+        };
+        let err = parse_xml_with("<cap:alert/>", &options).unwrap_err();
+        assert!(matches!(err, XmlError::Malformed { .. }));
+    }
 }
@@ -1,142 +1,1002 @@
+pub mod cache;
+pub mod client;
+pub mod daily;
+pub mod derived;
+pub mod error;
+pub mod location;
+pub mod measurement;
+pub mod metadata;
+pub mod model;
+pub mod symbol;
+pub mod timezone;
+pub mod units;
+
 use crate::components::linechart::LineChartData;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
-use super::generic::XMLItem;
+use self::{
+    error::ForecastError, location::GridPoint, measurement::Measurement,
+    metadata::ForecastMetadata, model::Model, symbol::SymbolSpan, timezone::DisplayZone,
+    units::Units,
+};
+use super::{
+    generic::{parse_tag, parse_xml, Position, XMLNode, XmlError},
+    write::WriteOptions,
+};
 
-use chrono::{DateTime, NaiveDateTime, Utc};
-use reqwest::Client;
+use chrono::{DateTime, Duration, Utc};
+use quick_xml::{events::Event, reader::Reader};
 
-/// Fetches the weather forecast data from MET Eireann
-pub async fn get_weather(base_url: &str, latitude: f64, longitude: f64) -> Result<String, String> {
-    let url = format!(
-        "{}/locationforecast?lat={:.6};long={:.6}",
-        base_url, latitude, longitude
-    );
+#[cfg(test)]
+use self::model::WeatherData;
+#[cfg(test)]
+use super::generic::XMLItem;
+#[cfg(test)]
+use quick_xml::DeError;
 
-    let client = Client::new();
-    client
-        .get(&url)
-        .send()
-        .await
-        .map_err(|err| format!("Failed to fetch data: {}", err))?
-        .text()
-        .await
-        .map_err(|err| format!("Failed to read response: {}", err))
+/// Deserializes a response into the typed `WeatherData` model
+#[cfg(test)]
+pub fn parse_forecast(data: &str) -> Result<WeatherData, DeError> {
+    quick_xml::de::from_str(data)
 }
 
 /// Represents a single measurement for a given time point
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct FlatItem {
-    pub from: NaiveDateTime,
-    pub to: NaiveDateTime,
-    pub name: String,
-    pub attributes: HashMap<String, String>,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub location: GridPoint,
+    pub measurement: Measurement,
+}
+
+/// Everything read out of a response: the measurements plus where they came from
+#[derive(Debug, PartialEq)]
+pub struct FlatForecast {
+    pub metadata: ForecastMetadata,
+    pub items: Vec<FlatItem>,
+}
+
+/// Whether a value applies to a single point in time or a whole period
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeSpan {
+    /// Eg. temperature, wind, cloud
+    Instant,
+    /// Eg. precipitation or min/max temperature over 1, 3 or 6 hours
+    Interval(Duration),
+}
+
+impl FlatItem {
+    pub fn span(&self) -> TimeSpan {
+        if self.to > self.from {
+            TimeSpan::Interval(self.to - self.from)
+        } else {
+            TimeSpan::Instant
+        }
+    }
+
+    /// Splits an accumulated value evenly into hourly steps, so it lines up with hourly values
+    /// and adds back up to the original total. Everything else stays at `from` as it is.
+    fn hourly_steps(&self, value: f32) -> Vec<(DateTime<Utc>, f32)> {
+        match self.span() {
+            TimeSpan::Interval(duration) if self.measurement.is_accumulated() => {
+                let steps = (duration.num_minutes() / 60).max(1) as i32;
+                (0..steps)
+                    .map(|step| (self.from + duration / steps * step, value / steps as f32))
+                    .collect()
+            }
+            _ => vec![(self.from, value)],
+        }
+    }
+}
+
+/// The forecast has overlapping 1h, 3h and 6h intervals starting at the same times. This keeps
+/// a chain of intervals for each measurement where each starts as the one before ends, so
+/// nothing is counted twice. The chain covers as much of the forecast as possible, using the
+/// shortest intervals that still join up. Instantaneous items are all kept, and everything
+/// comes back sorted by time.
+pub fn dedupe_intervals(items: &[FlatItem]) -> Vec<&FlatItem> {
+    let mut instants = vec![];
+    let mut intervals = HashMap::<&str, Vec<&FlatItem>>::new();
+    for item in items {
+        match item.span() {
+            TimeSpan::Instant => instants.push(item),
+            TimeSpan::Interval(_) => intervals
+                .entry(item.measurement.name())
+                .or_default()
+                .push(item),
+        }
+    }
+
+    let mut kept = instants;
+    for (_, intervals) in intervals {
+        kept.extend(contiguous_chain(intervals));
+    }
+    kept.sort_by_key(|item| (item.from, item.to));
+    kept
+}
+
+/// Picks the intervals for `dedupe_intervals`, all for the same measurement. Working back from
+/// the end, each start time remembers the best chain onwards from it: the most time covered,
+/// then the most (so shortest) intervals. Gaps are only left where no interval starts at the
+/// end of the one before.
+fn contiguous_chain(mut intervals: Vec<&FlatItem>) -> Vec<&FlatItem> {
+    intervals.sort_by_key(|item| (item.from, item.to));
+    let starts = intervals
+        .iter()
+        .map(|item| item.from)
+        .collect::<BTreeSet<_>>();
+
+    // For each start time: (time covered, intervals used, which interval to take first)
+    let mut best = HashMap::<DateTime<Utc>, (Duration, usize, usize)>::new();
+    // Carrying on from a time no interval starts at means skipping to the next one that does
+    let onwards = |best: &HashMap<_, (Duration, usize, usize)>, time: DateTime<Utc>| {
+        starts
+            .range(time..)
+            .next()
+            .and_then(|start| best.get(start))
+            .map(|(covered, count, _)| (*covered, *count))
+            .unwrap_or((Duration::zero(), 0))
+    };
+    for start in starts.iter().rev() {
+        let choice = intervals
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| item.from == *start)
+            .map(|(index, item)| {
+                let (covered, count) = onwards(&best, item.to);
+                (covered + (item.to - item.from), count + 1, index)
+            })
+            .max_by_key(|(covered, count, _)| (*covered, *count));
+        if let Some(choice) = choice {
+            best.insert(*start, choice);
+        }
+    }
+
+    let mut chain = vec![];
+    let mut time = starts.first().copied();
+    while let Some(start) = time.and_then(|time| starts.range(time..).next()) {
+        let item = intervals[best[start].2];
+        chain.push(item);
+        time = Some(item.to);
+    }
+    chain
+}
+
+/// Reads one of the `from`/`to` timestamps off a `<time>` element
+fn parse_time_attribute(
+    attributes: &HashMap<String, String>,
+    key: &str,
+) -> Result<DateTime<Utc>, ForecastError> {
+    let value = attributes.get(key).ok_or_else(|| {
+        ForecastError::Schema(format!(
+            "<time> has no '{}' attribute: {:?}",
+            key, attributes
+        ))
+    })?;
+
+    value.parse::<DateTime<Utc>>().map_err(|err| {
+        ForecastError::Schema(format!("Invalid '{}' timestamp {:?}: {}", key, value, err))
+    })
+}
+
+fn no_location_error(attributes: &HashMap<String, String>) -> ForecastError {
+    ForecastError::Schema(format!("<time> has no <location>: {:?}", attributes))
+}
+
+/// Reads the `created` timestamp and model runs out of an already-parsed tree
+#[cfg(test)]
+pub fn forecast_metadata(root: &XMLItem) -> Result<ForecastMetadata, ForecastError> {
+    Ok(ForecastMetadata {
+        created: ForecastMetadata::created_from_attributes(&root.attributes),
+        models: root
+            .select("meta/model")?
+            .filter_map(|model| Model::from_attributes(&model.attributes))
+            .collect(),
+    })
 }
 
 /// Turns semi-raw XML strucure into long-format data
-pub fn flatten_response(root: &XMLItem) -> Vec<FlatItem> {
+#[cfg(test)]
+pub fn flatten_response(root: &XMLItem) -> Result<Vec<FlatItem>, ForecastError> {
     // weatherdata
     //      product
     //          time to=X, from=X
     //              location
     //                  measurement k=v
 
-    root.children
-        .iter()
-        .find(|x| x.name == "product")
-        .expect("Failed to get 'product' xml element")
-        .children
-        .iter()
-        .flat_map(|time| {
-            time.children
-                .first()
-                .expect("Timestep is empty!")
-                .children
-                .iter()
-                .map(|measurement| {
-                    let to = time
-                        .attributes
-                        .get("to")
-                        .unwrap_or_else(|| panic!("Tag has no 'to' attribute: {:?}", measurement))
-                        .parse::<DateTime<Utc>>()
-                        .expect("Failed to parse timestamp")
-                        .naive_utc();
-                    let from = time
-                        .attributes
-                        .get("from")
-                        .unwrap_or_else(|| panic!("Tag has no 'from' attribute: {:?}", measurement))
-                        .parse::<DateTime<Utc>>()
-                        .expect("Failed to parse timestamp")
-                        .naive_utc();
-
-                    FlatItem {
-                        to,
-                        from,
-                        name: measurement.name.clone(),
-                        attributes: measurement.attributes.clone(),
+    let product = root
+        .first("product")?
+        .ok_or_else(|| ForecastError::Schema("No <product> element".to_string()))?;
+
+    let mut items = vec![];
+    for time in product.select("time")? {
+        let to = parse_time_attribute(&time.attributes, "to")?;
+        let from = parse_time_attribute(&time.attributes, "from")?;
+
+        let mut locations = time.select("location")?.peekable();
+        if locations.peek().is_none() {
+            return Err(no_location_error(&time.attributes));
+        }
+
+        for location in locations {
+            let grid_point = GridPoint::from_attributes(&location.attributes);
+            for measurement in location.children() {
+                items.push(FlatItem {
+                    to,
+                    from,
+                    location: grid_point.clone(),
+                    measurement: Measurement::from_attributes(
+                        measurement.name.clone(),
+                        measurement.attributes.clone(),
+                    )?,
+                });
+            }
+        }
+    }
+
+    Ok(items)
+}
+
+/// The `<time>` block that the reader is currently inside
+struct TimeContext {
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    depth: usize,
+    seen_location: bool,
+    /// Kept for error messages
+    attributes: HashMap<String, String>,
+}
+
+/// Just the measurements from `stream_forecast`
+#[cfg(test)]
+pub fn stream_flat_items(data: &str) -> Result<Vec<FlatItem>, ForecastError> {
+    stream_forecast(data).map(|forecast| forecast.items)
+}
+
+/// Same output as `flatten_response` and `forecast_metadata` on `parse_xml(data)?`, but reads
+/// everything straight out of the XML events rather than building a full `XMLItem` tree first
+pub fn stream_forecast(data: &str) -> Result<FlatForecast, ForecastError> {
+    let mut reader = Reader::from_str(data);
+    let mut items = vec![];
+    let mut metadata = ForecastMetadata::default();
+
+    // Depth of the element being read, with the root element at 0
+    let mut depth = 0;
+    let mut seen_root = false;
+    let mut seen_product = false;
+    // Set while inside the first `<product>`, the only one read, like `flatten_response`
+    let mut product_depth = None;
+    let mut in_meta = false;
+    let mut time: Option<TimeContext> = None;
+    let mut location_depth = None;
+    let mut grid_point = GridPoint::default();
+
+    loop {
+        let event = reader.read_event().map_err(|err| {
+            XmlError::from_quick_xml(err, Position::in_document(data, reader.error_position()))
+        })?;
+        let position = Position::in_document(data, reader.buffer_position());
+
+        let (bytes_start, has_children) = match event {
+            Event::Start(bytes_start) => (bytes_start, true),
+            Event::Empty(bytes_start) => (bytes_start, false),
+            Event::End(_) => {
+                depth -= 1;
+                if depth == 1 {
+                    in_meta = false;
+                }
+                if location_depth == Some(depth) {
+                    location_depth = None;
+                }
+                if product_depth == Some(depth) {
+                    product_depth = None;
+                }
+                if let Some(closed) = time.take_if(|time| time.depth == depth) {
+                    if !closed.seen_location {
+                        return Err(no_location_error(&closed.attributes));
                     }
-                })
+                }
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+        let local_name = bytes_start.local_name();
+        if !seen_root {
+            seen_root = true;
+            let tag = parse_tag(bytes_start, position, None)?;
+            metadata.created = ForecastMetadata::created_from_attributes(&tag.attributes);
+        } else if location_depth.is_some_and(|location_depth| location_depth + 1 == depth) {
+            // A measurement inside the location
+            let time = time
+                .as_ref()
+                .expect("Location is always inside a time block");
+            let tag = parse_tag(bytes_start, position, None)?;
+            items.push(FlatItem {
+                from: time.from,
+                to: time.to,
+                location: grid_point.clone(),
+                measurement: Measurement::from_attributes(tag.name, tag.attributes)?,
+            });
+        } else if local_name.as_ref() == b"location" {
+            if let Some(time) = time.as_mut().filter(|time| time.depth + 1 == depth) {
+                time.seen_location = true;
+                location_depth = Some(depth);
+                grid_point =
+                    GridPoint::from_attributes(&parse_tag(bytes_start, position, None)?.attributes);
+            }
+        } else if local_name.as_ref() == b"time"
+            && product_depth.is_some_and(|product_depth| product_depth + 1 == depth)
+        {
+            let tag = parse_tag(bytes_start, position, None)?;
+            if !has_children {
+                return Err(no_location_error(&tag.attributes));
+            }
+            time = Some(TimeContext {
+                from: parse_time_attribute(&tag.attributes, "from")?,
+                to: parse_time_attribute(&tag.attributes, "to")?,
+                depth,
+                seen_location: false,
+                attributes: tag.attributes,
+            });
+        } else if local_name.as_ref() == b"product" && depth == 1 {
+            if !seen_product && has_children {
+                product_depth = Some(depth);
+            }
+            seen_product = true;
+        } else if local_name.as_ref() == b"meta" && depth == 1 {
+            in_meta = has_children;
+        } else if local_name.as_ref() == b"model" && in_meta && depth == 2 {
+            let tag = parse_tag(bytes_start, position, None)?;
+            metadata
+                .models
+                .extend(Model::from_attributes(&tag.attributes));
+        }
+
+        if has_children {
+            depth += 1;
+        } else if location_depth == Some(depth) {
+            location_depth = None;
+        }
+    }
+
+    if depth > 0 {
+        return Err(XmlError::Malformed {
+            message: "Document ended before all elements were closed".to_string(),
+            position: Position::in_document(data, reader.buffer_position()),
+        }
+        .into());
+    }
+    if !seen_root {
+        return Err(XmlError::EmptyDocument.into());
+    }
+    if !seen_product {
+        return Err(ForecastError::Schema("No <product> element".to_string()));
+    }
+
+    Ok(FlatForecast { metadata, items })
+}
+
+/// Every distinct location in the forecast, in the order they first appear
+pub fn grid_points(items: &[FlatItem]) -> Vec<&GridPoint> {
+    let mut points = vec![];
+    for item in items {
+        if !points.contains(&&item.location) {
+            points.push(&item.location);
+        }
+    }
+    points
+}
+
+/// Keeps only the measurements for the location closest to the given coordinates. Locations
+/// without coordinates are only used if there's nothing else.
+pub fn nearest_location(items: Vec<FlatItem>, latitude: f64, longitude: f64) -> Vec<FlatItem> {
+    let Some(nearest) = grid_points(&items)
+        .into_iter()
+        .min_by(|a, b| {
+            let a = a.distance_km(latitude, longitude).unwrap_or(f64::INFINITY);
+            let b = b.distance_km(latitude, longitude).unwrap_or(f64::INFINITY);
+            a.total_cmp(&b)
         })
+        .cloned()
+    else {
+        return items;
+    };
+
+    items
+        .into_iter()
+        .filter(|item| item.location == nearest)
         .collect()
 }
 
-/// Parses out data into separate measurements for plotting
-pub fn prepare_plot_data(items: &[FlatItem]) -> Vec<LineChartData> {
-    // Sort by date
-    let mut items = items.iter().collect::<Vec<_>>();
-    items.sort_by_key(|item| item.from);
+/// Writes a response back out with only the given location's measurements and none of the
+/// whitespace, so it takes much less room to save. `stream_forecast` reads the same items for
+/// that location from it as from the original.
+pub fn compact_forecast(xml: &str, location: &GridPoint) -> Result<String, ForecastError> {
+    let mut root = parse_xml(xml)?;
+    let mut seen_product = false;
+    root.nodes.retain_mut(|node| {
+        let XMLNode::Element(product) = node else {
+            return true;
+        };
+        if product.name != "product" {
+            return true;
+        }
+        // Only the first product is ever read
+        if std::mem::replace(&mut seen_product, true) {
+            return false;
+        }
+        product.nodes.retain_mut(|node| {
+            let XMLNode::Element(time) = node else {
+                return true;
+            };
+            if time.name != "time" {
+                return true;
+            }
+            time.nodes.retain(|node| match node {
+                XMLNode::Element(item) if item.name == "location" => {
+                    GridPoint::from_attributes(&item.attributes) == *location
+                }
+                _ => true,
+            });
+            time.children().any(|item| item.name == "location")
+        });
+        true
+    });
+
+    Ok(root.to_xml_string_with(&WriteOptions {
+        declaration: true,
+        ..Default::default()
+    }))
+}
+
+/// How `prepare_plot_data` should present the values
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PlotOptions {
+    pub units: Units,
+    /// Which zone the time labels are in
+    pub timezone: DisplayZone,
+}
+
+/// Charts ready to render, plus a record of anything that couldn't be charted properly
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PlotData {
+    pub charts: Vec<LineChartData>,
+    /// Weather icons over time
+    pub symbols: Vec<SymbolSpan>,
+    /// Where the forecast is actually for
+    pub location: Option<GridPoint>,
+    /// Unrecognised measurements which were plotted with a guessed unit
+    pub generic: Vec<String>,
+    /// Unrecognised measurements with nothing numeric to plot
+    pub skipped: Vec<String>,
+}
+
+/// Finds the chart for a key, starting a new one if this is its first value
+fn chart_entry<'a>(
+    charts: &'a mut HashMap<String, LineChartData>,
+    key: &str,
+    unit: Option<String>,
+    options: &PlotOptions,
+) -> &'a mut LineChartData {
+    charts
+        .entry(key.to_string())
+        .or_insert_with(|| LineChartData {
+            key: key.to_string(),
+            title: Some(key.to_string()),
+            y_axis_title: unit,
+            time_zone: options.timezone.name().map(str::to_string),
+            ..Default::default()
+        })
+}
+
+/// Parses out data into separate measurements for plotting, converted to the given units with
+/// ISO-8601 time labels. Expects the items to all be for the same location, see
+/// `nearest_location`.
+pub fn prepare_plot_data(items: &[FlatItem], options: &PlotOptions) -> PlotData {
+    let location = items.first().map(|item| item.location.clone());
+    let items = dedupe_intervals(items);
 
     let mut measurement_groups = HashMap::<_, LineChartData>::new();
-    items.into_iter().for_each(|item| {
-        // Each measurement has it's own format, so grab the value out specifically for each one
-        // Some measurements (like precipitation) provide an interval aswell
-        let value = match item.name.as_str() {
-            "temperature"
-            | "precipitation"
-            | "globalRadiation"
-            | "humidity"
-            | "pressure"
-            | "dewpointTemperature" => item.attributes.get("value").unwrap().parse().unwrap(),
-            // Direction 0-360 degrees
-            "windDirection" => item.attributes.get("deg").unwrap().parse().unwrap(),
-            "windSpeed" | "windGust" => item.attributes.get("mps").unwrap().parse().unwrap(),
-            // High/med/low clouds are % of each type I think?
-            "cloudiness" | "lowClouds" | "mediumClouds" | "highClouds" => {
-                item.attributes.get("percent").unwrap().parse().unwrap()
+    let mut symbols = vec![];
+    let mut generic = BTreeSet::new();
+    let mut skipped = BTreeSet::new();
+    items.iter().for_each(|item| {
+        if let Measurement::Symbol { id, .. } = &item.measurement {
+            match item.measurement.symbol() {
+                Some(symbol) => symbols.push(SymbolSpan {
+                    from: item.from,
+                    to: item.to,
+                    label: options.timezone.format(&item.from, "%a %H:%M"),
+                    symbol,
+                }),
+                None => {
+                    skipped.insert(format!("symbol {:?}", id));
+                }
             }
-            // Symbol is just an index corresponding to some icon Eg. a cloud
-            "symbol" => return,
-            _ => todo!("Not implemented: {:?}", item),
-        };
+        }
+
+        let series = item.measurement.series(&options.units);
+
+        if let Measurement::Unknown { name, .. } = &item.measurement {
+            if series.is_empty() {
+                skipped.insert(name.clone());
+            } else {
+                generic.insert(name.clone());
+            }
+        }
 
-        measurement_groups
-            .entry(item.name.clone())
-            .and_modify(|data| {
-                data.x_data.push(item.from.to_string());
+        series.into_iter().for_each(|series| {
+            let data = chart_entry(&mut measurement_groups, &series.key, series.unit, options);
+            for (time, value) in item.hourly_steps(series.value) {
+                data.x_data.push(options.timezone.iso_label(&time));
                 data.y_data.push(value);
-            })
-            .or_insert_with(|| {
-                let y_axis_title = match item.name.as_str() {
-                    "temperature" | "dewpointTemperature" => "Celcius",
-                    "precipitation" => "Millimetres",
-                    "windDirection" => "Degrees",
-                    "windSpeed" | "windGust" => "Miles per Hour",
-                    "globalRadiation" => "Watts per m^2",
-                    "humidity" | "cloudiness" | "lowClouds" | "mediumClouds" | "highClouds" => {
-                        "Percent"
-                    }
-                    "pressure" => "hPa",
-                    _ => todo!("Not implemented: {:?}", item),
-                };
-
-                LineChartData {
-                    key: item.name.clone(),
-                    title: Some(item.name.clone()),
-                    y_axis_title: Some(y_axis_title.to_string()),
-                    ..Default::default()
-                }
-            });
+            }
+        });
     });
 
-    measurement_groups.into_values().collect()
+    // Extra series like feels-like temperature, worked out from the ones above
+    for (time, derived, value) in derived::derive(&items) {
+        let (value, unit) = match derived.quantity() {
+            Some(quantity) => (
+                options.units.convert(quantity, value),
+                options.units.label(quantity),
+            ),
+            None => (value, derived.unit_label().unwrap_or_default()),
+        };
+        let data = chart_entry(
+            &mut measurement_groups,
+            derived.key(),
+            Some(unit.to_string()),
+            options,
+        );
+        data.x_data.push(options.timezone.iso_label(&time));
+        data.y_data.push(value);
+    }
+
+    PlotData {
+        charts: measurement_groups.into_values().collect(),
+        symbols,
+        location,
+        generic: generic.into_iter().collect(),
+        skipped: skipped.into_iter().collect(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::xml::generic::parse_xml;
+    use chrono::{TimeZone, Timelike};
+    use symbol::WeatherSymbol;
+
+    const DATA: &str = include_str!("test_data/locationforecast.xml");
+
+    #[test]
+    fn test_stream_matches_tree() {
+        let streamed = stream_flat_items(DATA).unwrap();
+        let flattened = flatten_response(&parse_xml(DATA).unwrap()).unwrap();
+
+        assert_eq!(streamed.len(), 70);
+        assert_eq!(streamed, flattened);
+    }
+
+    #[test]
+    fn test_stream_metadata() {
+        let metadata = stream_forecast(DATA).unwrap().metadata;
+
+        assert_eq!(
+            metadata,
+            forecast_metadata(&parse_xml(DATA).unwrap()).unwrap()
+        );
+        assert_eq!(
+            metadata.created,
+            "2025-01-14T10:02:19Z".parse::<DateTime<Utc>>().ok()
+        );
+        assert_eq!(
+            metadata.models,
+            parse_forecast(DATA).unwrap().meta.unwrap().models
+        );
+        assert_eq!(
+            metadata
+                .summary(&DisplayZone::parse("UTC").unwrap())
+                .unwrap(),
+            "HARMONIE run 06Z, next update 16:00"
+        );
+
+        // Metadata is optional
+        let bare = r#"<weatherdata><product/></weatherdata>"#;
+        assert_eq!(
+            stream_forecast(bare).unwrap().metadata,
+            ForecastMetadata::default()
+        );
+    }
+
+    #[test]
+    fn test_parse_forecast() {
+        let forecast = parse_forecast(DATA).unwrap();
+
+        assert_eq!(
+            forecast.created,
+            "2025-01-14T10:02:19Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        let models = forecast.meta.unwrap().models;
+        assert_eq!(models.len(), 3);
+        assert_eq!(models[0].name, "harmonie");
+
+        let times = forecast.product.times;
+        assert_eq!(times.len(), 10);
+
+        let location = &times[0].locations[0];
+        assert_eq!(location.altitude, Some(60.));
+        assert_eq!(location.temperature.as_ref().unwrap().value, 4.6);
+        assert_eq!(
+            location.temperature.as_ref().unwrap().unit.as_deref(),
+            Some("celsius")
+        );
+        assert_eq!(location.wind_speed.as_ref().unwrap().beaufort, Some(3));
+        assert_eq!(location.wind_gust.as_ref().unwrap().beaufort, None);
+        assert_eq!(
+            location.wind_direction.as_ref().unwrap().name.as_deref(),
+            Some("W")
+        );
+        assert_eq!(location.low_clouds.as_ref().unwrap().percent, 97.7);
+        assert!(location.precipitation.is_none());
+
+        let location = &times[3].locations[0];
+        let precipitation = location.precipitation.as_ref().unwrap();
+        assert_eq!(precipitation.value, 0.1);
+        assert_eq!(precipitation.maxvalue, Some(0.2));
+        assert_eq!(precipitation.probability, Some(15.));
+        assert_eq!(location.symbol.as_ref().unwrap().number, 46);
+        assert!(location.temperature.is_none());
+    }
+
+    #[test]
+    fn test_stream_first_product() {
+        // Only `<time>`s directly inside the first product are forecast steps
+        let data = r#"<weatherdata>
+            <meta><time from="2025-01-01T00:00:00Z" to="2025-01-01T00:00:00Z"/></meta>
+            <product>
+                <time from="2025-01-01T00:00:00Z" to="2025-01-01T00:00:00Z">
+                    <location latitude="53.0" longitude="-6.0"><temperature value="1"/></location>
+                </time>
+                <extra>
+                    <time from="2025-01-01T01:00:00Z" to="2025-01-01T01:00:00Z">
+                        <location latitude="53.0" longitude="-6.0"><temperature value="2"/></location>
+                    </time>
+                </extra>
+            </product>
+            <product>
+                <time from="2025-01-01T02:00:00Z" to="2025-01-01T02:00:00Z">
+                    <location latitude="53.0" longitude="-6.0"><temperature value="3"/></location>
+                </time>
+            </product>
+        </weatherdata>"#;
+        let items = stream_flat_items(data).unwrap();
+
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].measurement, Measurement::Temperature(1.));
+        assert_eq!(items, flatten_response(&parse_xml(data).unwrap()).unwrap());
+    }
+
+    #[test]
+    fn test_stream_multiple_locations() {
+        let data = r#"<weatherdata><product>
+            <time from="2025-01-01T00:00:00Z" to="2025-01-01T00:00:00Z">
+                <location id="a" latitude="53.0" longitude="-6.0"><temperature value="1"/></location>
+                <location id="b" latitude="54.0" longitude="-6.0" altitude="20"><temperature value="2"/></location>
+            </time>
+            <time from="2025-01-01T01:00:00Z" to="2025-01-01T01:00:00Z">
+                <location id="a" latitude="53.0" longitude="-6.0"><temperature value="3"/></location>
+                <location id="b" latitude="54.0" longitude="-6.0" altitude="20"><temperature value="4"/></location>
+            </time>
+        </product></weatherdata>"#;
+        let items = stream_flat_items(data).unwrap();
+
+        assert_eq!(items.len(), 4);
+        assert_eq!(items, flatten_response(&parse_xml(data).unwrap()).unwrap());
+        assert_eq!(items[1].location.id.as_deref(), Some("b"));
+        assert_eq!(items[1].location.altitude, Some(20.));
+        assert_eq!(items[1].measurement, Measurement::Temperature(2.));
+
+        let points = grid_points(&items);
+        assert_eq!(points.len(), 2);
+
+        let plot = prepare_plot_data(
+            &nearest_location(items, 53.9, -6.1),
+            &PlotOptions::default(),
+        );
+        assert_eq!(plot.charts[0].y_data, vec![2., 4.]);
+        assert_eq!(plot.location.unwrap().id.as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn test_prepare_plot_data() {
+        let items = stream_flat_items(DATA).unwrap();
+        let utc = PlotOptions {
+            timezone: DisplayZone::parse("UTC").unwrap(),
+            ..Default::default()
+        };
+        let PlotData {
+            charts,
+            symbols,
+            location: plot_location,
+            generic,
+            skipped,
+        } = prepare_plot_data(&items, &utc);
+
+        // Everything except the symbol is plotted, plus everything derived apart from the
+        // heat index since it's January
+        assert_eq!(charts.len(), 13 + 6);
+        let feels_like = charts.iter().find(|x| x.key == "feelsLike").unwrap();
+        assert_eq!(feels_like.y_data.len(), 5);
+        assert_eq!(feels_like.y_axis_title.as_deref(), Some("Celsius"));
+        assert!(feels_like.y_data[0] < 4.6);
+        assert!(generic.is_empty());
+        assert!(skipped.is_empty());
+        assert_eq!(plot_location.unwrap().altitude, Some(60.));
+
+        let weather = symbols.iter().map(|x| x.symbol.weather).collect::<Vec<_>>();
+        assert_eq!(
+            weather,
+            vec![
+                WeatherSymbol::Cloud,
+                WeatherSymbol::Drizzle,
+                WeatherSymbol::LightRainSun,
+                WeatherSymbol::Rain,
+                WeatherSymbol::PartlyCloud
+            ]
+        );
+        assert_eq!(symbols[3].to - symbols[3].from, Duration::hours(3));
+
+        // Times stay in UTC all the way through, and are only shifted for the labels
+        assert_eq!(
+            items[0].from,
+            "2025-01-14T11:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        let dublin = PlotOptions {
+            timezone: DisplayZone::parse("Europe/Dublin").unwrap(),
+            ..Default::default()
+        };
+        let summer = DATA.replace("2025-01-14", "2025-07-14");
+        let summer_charts = prepare_plot_data(&stream_flat_items(&summer).unwrap(), &dublin).charts;
+        let summer_temperature = summer_charts
+            .iter()
+            .find(|x| x.key == "temperature")
+            .unwrap();
+        assert_eq!(summer_temperature.x_data[0], "2025-07-14T12:00:00+01:00");
+        assert_eq!(
+            summer_temperature.time_zone.as_deref(),
+            Some("Europe/Dublin")
+        );
+        let temperature = charts.iter().find(|x| x.key == "temperature").unwrap();
+        assert_eq!(temperature.y_data, vec![4.6, 5.2, 5.9, 4.8, 2.1]);
+        assert_eq!(temperature.y_axis_title.as_deref(), Some("Celsius"));
+
+        // The 3h and 6h intervals are spread out hourly without changing the total
+        let precipitation = charts.iter().find(|x| x.key == "precipitation").unwrap();
+        assert_eq!(precipitation.y_data.len(), 12);
+        assert_eq!(precipitation.x_data[3], "2025-01-14T13:00:00+00:00");
+        assert_eq!(precipitation.x_data[4], "2025-01-14T14:00:00+00:00");
+        assert_eq!(precipitation.y_data[4], 0.4);
+        let total = precipitation.y_data.iter().sum::<f32>();
+        assert!((total - 2.0).abs() < 1e-5, "Total was {}", total);
+    }
+
+    #[test]
+    fn test_dedupe_intervals() {
+        let interval = |from: u32, to: u32, value: f32| FlatItem {
+            from: Utc.with_ymd_and_hms(2025, 1, 1, from, 0, 0).unwrap(),
+            to: Utc.with_ymd_and_hms(2025, 1, 1, to, 0, 0).unwrap(),
+            location: GridPoint::default(),
+            measurement: Measurement::Precipitation {
+                value,
+                min: None,
+                max: None,
+                probability: None,
+            },
+        };
+        let items = vec![
+            interval(10, 16, 1.5),
+            interval(10, 11, 0.1),
+            interval(10, 13, 0.6),
+            interval(11, 12, 0.2),
+            interval(12, 13, 0.3),
+            interval(13, 19, 1.2),
+            interval(10, 10, 5.),
+        ];
+
+        let kept = dedupe_intervals(&items)
+            .into_iter()
+            .map(|item| (item.from.hour(), item.to.hour()))
+            .collect::<Vec<_>>();
+        assert_eq!(kept, vec![(10, 10), (10, 11), (11, 12), (12, 13), (13, 19)]);
+        assert_eq!(items[0].span(), TimeSpan::Interval(Duration::hours(6)));
+        assert_eq!(items[6].span(), TimeSpan::Instant);
+
+        let precipitation = &prepare_plot_data(&items, &PlotOptions::default()).charts[0];
+        assert_eq!(precipitation.y_data.len(), 1 + 3 + 6);
+        assert_eq!(precipitation.y_data[..4], [5., 0.1, 0.2, 0.3]);
+        assert!(precipitation.y_data[4..].iter().all(|x| *x == 0.2));
+
+        // Only the first hour has a 1h interval. Keeping it would leave 11:00 to 13:00
+        // uncovered, so the 3h ones are used from the start instead.
+        let items = vec![
+            interval(10, 11, 0.1),
+            interval(10, 13, 0.3),
+            interval(10, 16, 0.6),
+            interval(13, 16, 0.3),
+            interval(16, 22, 1.2),
+        ];
+        let kept = dedupe_intervals(&items)
+            .into_iter()
+            .map(|item| (item.from.hour(), item.to.hour()))
+            .collect::<Vec<_>>();
+        assert_eq!(kept, vec![(10, 13), (13, 16), (16, 22)]);
+        assert!(kept.windows(2).all(|pair| pair[0].1 == pair[1].0));
+
+        let precipitation = &prepare_plot_data(&items, &PlotOptions::default()).charts[0];
+        assert_eq!(precipitation.y_data.len(), 12);
+        assert!(precipitation
+            .y_data
+            .iter()
+            .all(|x| (x - 0.1).abs() < 1e-6 || (x - 0.2).abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_compact_forecast() {
+        // A second location, as if the request was between two grid points
+        let other = DATA.replace(
+            r#"<location altitude="60" latitude="53.3627" longitude="-6.3111">"#,
+            r#"<location altitude="60" latitude="53.3627" longitude="-6.3111">
+                <temperature id="TTT" unit="celsius" value="1.0"/>
+            </location>
+            <location altitude="12" latitude="53.4" longitude="-6.2">"#,
+        );
+        let nearest = nearest_location(stream_flat_items(&other).unwrap(), 53.3627, -6.3111);
+        let compact = compact_forecast(&other, &nearest[0].location).unwrap();
+
+        assert!(compact.len() * 2 < other.len());
+        assert!(!compact.contains(r#"latitude="53.4""#));
+        let forecast = stream_forecast(&compact).unwrap();
+        assert_eq!(forecast.items, nearest);
+        assert_eq!(forecast.metadata, stream_forecast(&other).unwrap().metadata);
+    }
+
+    #[test]
+    fn test_prepare_plot_data_fall_back() {
+        // Clocks in Dublin go back at 01:00 UTC on 2025-10-26, so 01:00 local happens twice
+        let items =
+            stream_flat_items(include_str!("test_data/locationforecast_fallback.xml")).unwrap();
+        let dublin = PlotOptions {
+            timezone: DisplayZone::parse("Europe/Dublin").unwrap(),
+            ..Default::default()
+        };
+        let charts = prepare_plot_data(&items, &dublin).charts;
+
+        for key in ["temperature", "precipitation"] {
+            let chart = charts.iter().find(|x| x.key == key).unwrap();
+            assert_eq!(
+                chart.x_data,
+                vec![
+                    "2025-10-26T00:00:00+01:00",
+                    "2025-10-26T01:00:00+01:00",
+                    "2025-10-26T01:00:00+00:00",
+                    "2025-10-26T02:00:00+00:00",
+                    "2025-10-26T03:00:00+00:00",
+                ][..chart.x_data.len()]
+            );
+
+            // The chart reads the offsets too, so the repeated hour still comes out an hour
+            // apart instead of on top of each other
+            let times = chart
+                .x_data
+                .iter()
+                .map(|label| label.parse::<DateTime<Utc>>().unwrap())
+                .collect::<Vec<_>>();
+            assert!(times
+                .windows(2)
+                .all(|pair| pair[1] - pair[0] == Duration::hours(1)));
+        }
+    }
+
+    #[test]
+    fn test_prepare_plot_data_unknown() {
+        let data = r#"<weatherdata><product>
+            <time from="2025-01-01T00:00:00Z" to="2025-01-01T00:00:00Z">
+                <location>
+                    <temperature unit="celsius" value="1"/>
+                    <fog id="FOG" percent="2.5"/>
+                    <warning level="yellow"/>
+                </location>
+            </time>
+            <time from="2025-01-01T00:00:00Z" to="2025-01-01T06:00:00Z">
+                <location>
+                    <minTemperature id="TTT" unit="celsius" value="-1.5"/>
+                </location>
+            </time>
+        </product></weatherdata>"#;
+        let plot = prepare_plot_data(&stream_flat_items(data).unwrap(), &PlotOptions::default());
+
+        let mut keys = plot
+            .charts
+            .iter()
+            .map(|x| x.key.as_str())
+            .collect::<Vec<_>>();
+        keys.sort();
+        // Feels-like only needs the temperature, so it always shows up alongside it
+        assert_eq!(
+            keys,
+            vec![
+                "feelsLike",
+                "fog.percent",
+                "minTemperature.value",
+                "temperature"
+            ]
+        );
+        assert_eq!(plot.generic, vec!["fog", "minTemperature"]);
+        assert_eq!(plot.skipped, vec!["warning"]);
+
+        let fog = plot.charts.iter().find(|x| x.key == "fog.percent").unwrap();
+        // No unit attribute, so no guessing one
+        assert_eq!(fog.y_axis_title, None);
+        assert_eq!(fog.y_data, vec![2.5]);
+
+        // Every temperature chart follows the chosen units, not just the typed ones
+        let imperial = PlotOptions {
+            units: Units::imperial(),
+            ..Default::default()
+        };
+        let plot = prepare_plot_data(&stream_flat_items(data).unwrap(), &imperial);
+        let min_temperature = plot
+            .charts
+            .iter()
+            .find(|x| x.key == "minTemperature.value")
+            .unwrap();
+        assert_eq!(min_temperature.y_axis_title.as_deref(), Some("Fahrenheit"));
+        assert!(min_temperature
+            .y_data
+            .iter()
+            .all(|x| (x - 29.3).abs() < 1e-4));
+    }
+
+    #[test]
+    fn test_stream_errors() {
+        assert_eq!(
+            stream_flat_items("").unwrap_err(),
+            ForecastError::Parse(XmlError::EmptyDocument)
+        );
+        assert!(matches!(
+            stream_flat_items("<weatherdata><product>").unwrap_err(),
+            ForecastError::Parse(XmlError::Malformed { .. })
+        ));
+    }
+
+    #[test]
+    fn test_schema_errors() {
+        let cases = [
+            "<html><body>Too many requests</body></html>",
+            r#"<weatherdata><product><time from="2025-01-01T00:00:00Z"><location/></time></product></weatherdata>"#,
+            r#"<weatherdata><product><time from="yesterday" to="today"><location/></time></product></weatherdata>"#,
+            r#"<weatherdata><product><time from="2025-01-01T00:00:00Z" to="2025-01-01T00:00:00Z"/></product></weatherdata>"#,
+            r#"<weatherdata><product><time from="2025-01-01T00:00:00Z" to="2025-01-01T00:00:00Z"><location><temperature unit="celsius"/></location></time></product></weatherdata>"#,
+        ];
+
+        for data in cases {
+            assert!(
+                matches!(stream_flat_items(data), Err(ForecastError::Schema(_))),
+                "Streaming should fail on {}",
+                data
+            );
+            assert!(
+                matches!(
+                    flatten_response(&parse_xml(data).unwrap()),
+                    Err(ForecastError::Schema(_))
+                ),
+                "Flattening should fail on {}",
+                data
+            );
+        }
+    }
 }
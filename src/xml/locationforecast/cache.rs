@@ -0,0 +1,358 @@
+use std::cell::RefCell;
+
+use chrono::{DateTime, Utc};
+use gloo::storage::{LocalStorage, Storage};
+use reqwest::header::{HeaderMap, EXPIRES, LAST_MODIFIED};
+use serde::{Deserialize, Serialize};
+use web_sys::console;
+
+use super::client::ForecastClientConfig;
+
+/// Coordinates are rounded to this many decimal places for the cache key, about 1km. The
+/// forecast grid is coarser than that, so nearby clicks can share a response.
+const KEY_DECIMALS: i32 = 2;
+
+/// Prefix for our entries in `localStorage`, so they can be told apart from anything else
+const STORAGE_PREFIX: &str = "forecast-cache:";
+
+/// Our keys in `localStorage`, least recently used first, so the oldest can go first when
+/// it fills up
+const STORAGE_INDEX: &str = "forecast-cache-index";
+
+/// How many entries to remove from `localStorage` at a time when there isn't room
+const EVICT_BATCH: usize = 2;
+
+/// How many forecasts to keep in memory. Clicking around the map adds one for every spot.
+const MEMORY_ENTRIES: usize = 16;
+
+/// A raw forecast response along with what the server said about how long it's good for
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedForecast {
+    pub xml: String,
+    /// From the `Expires` header. Without one, the response is revalidated every time.
+    pub expires: Option<DateTime<Utc>>,
+    /// The `Last-Modified` header as sent, for `If-Modified-Since`
+    pub last_modified: Option<String>,
+    /// When we last got this from, or had it confirmed by, the server
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl CachedForecast {
+    /// A new entry for a fresh 200 response
+    pub fn from_response(xml: String, headers: &HeaderMap, now: DateTime<Utc>) -> Self {
+        Self {
+            xml,
+            expires: expires(headers),
+            last_modified: header_str(headers, LAST_MODIFIED),
+            fetched_at: now,
+        }
+    }
+
+    /// The same entry after a 304 Not Modified, with whatever new expiry the server gave
+    pub fn revalidated(&self, headers: &HeaderMap, now: DateTime<Utc>) -> Self {
+        Self {
+            xml: self.xml.clone(),
+            expires: expires(headers),
+            last_modified: header_str(headers, LAST_MODIFIED).or(self.last_modified.clone()),
+            fetched_at: now,
+        }
+    }
+
+    /// Whether it can be used without asking the server
+    pub fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        self.expires.is_some_and(|expires| now < expires)
+    }
+}
+
+fn header_str(headers: &HeaderMap, name: reqwest::header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// HTTP dates look like "Tue, 14 Jan 2025 12:00:00 GMT". Anything unparseable, like the "0"
+/// some servers send, counts as already expired.
+fn expires(headers: &HeaderMap) -> Option<DateTime<Utc>> {
+    header_str(headers, EXPIRES).map(|value| {
+        DateTime::parse_from_rfc2822(value.trim())
+            .map(|time| time.to_utc())
+            .unwrap_or(DateTime::<Utc>::MIN_UTC)
+    })
+}
+
+/// Rounds to the key precision, making sure -0.00 and 0.00 come out the same
+fn round_coordinate(value: f64) -> f64 {
+    let scale = 10f64.powi(KEY_DECIMALS);
+    (value * scale).round() / scale + 0.
+}
+
+/// Whether two positions round to the same cache key, so would get the same forecast
+pub fn same_key_position(a: (f64, f64), b: (f64, f64)) -> bool {
+    round_coordinate(a.0) == round_coordinate(b.0) && round_coordinate(a.1) == round_coordinate(b.1)
+}
+
+/// Identifies a forecast by where it was fetched from and the rounded position
+pub fn cache_key(config: &ForecastClientConfig, latitude: f64, longitude: f64) -> String {
+    format!(
+        "{}?lat={:.*};long={:.*}",
+        config.base_url.trim_end_matches('/'),
+        KEY_DECIMALS as usize,
+        round_coordinate(latitude),
+        KEY_DECIMALS as usize,
+        round_coordinate(longitude)
+    )
+}
+
+/// The most recently used forecasts, oldest first, up to a fixed number
+#[derive(Debug, Default)]
+struct RecentEntries {
+    entries: Vec<(String, CachedForecast)>,
+}
+
+impl RecentEntries {
+    /// Looks up an entry, counting it as just used
+    fn get(&mut self, key: &str) -> Option<CachedForecast> {
+        let index = self.entries.iter().position(|(k, _)| k == key)?;
+        let entry = self.entries.remove(index);
+        self.entries.push(entry);
+        self.entries.last().map(|(_, entry)| entry.clone())
+    }
+
+    /// Adds or replaces an entry, dropping the least recently used if there are too many
+    fn insert(&mut self, key: &str, entry: CachedForecast) {
+        self.entries.retain(|(k, _)| k != key);
+        self.entries.push((key.to_string(), entry));
+        let excess = self.entries.len().saturating_sub(MEMORY_ENTRIES);
+        self.entries.drain(..excess);
+    }
+}
+
+thread_local! {
+    static MEMORY: RefCell<RecentEntries> = RefCell::new(RecentEntries::default());
+}
+
+/// Looks in memory first, then in `localStorage` so it survives reloads
+pub fn load(key: &str) -> Option<CachedForecast> {
+    if let Some(entry) = MEMORY.with(|memory| memory.borrow_mut().get(key)) {
+        return Some(entry);
+    }
+
+    let entry = LocalStorage::get::<CachedForecast>(format!("{}{}", STORAGE_PREFIX, key)).ok()?;
+    touch_storage_index(key);
+    MEMORY.with(|memory| memory.borrow_mut().insert(key, entry.clone()));
+    Some(entry)
+}
+
+/// Saves to memory and `localStorage`. If storage is full, the least recently used of our
+/// entries are removed a few at a time until there's room, and if there's still no room it's
+/// only kept in memory.
+pub fn store(key: &str, entry: &CachedForecast) {
+    MEMORY.with(|memory| memory.borrow_mut().insert(key, entry.clone()));
+
+    let storage_key = format!("{}{}", STORAGE_PREFIX, key);
+    let mut evictable = eviction_order(&stored_keys(), &storage_index(), key).into_iter();
+    loop {
+        match LocalStorage::set(&storage_key, entry) {
+            Ok(()) => {
+                touch_storage_index(key);
+                return;
+            }
+            Err(err) => {
+                let batch = evictable.by_ref().take(EVICT_BATCH).collect::<Vec<_>>();
+                if batch.is_empty() {
+                    console::warn_1(
+                        &format!("Couldn't save forecast to localStorage: {}", err).into(),
+                    );
+                    return;
+                }
+                for key in batch {
+                    LocalStorage::delete(format!("{}{}", STORAGE_PREFIX, key));
+                }
+            }
+        }
+    }
+}
+
+/// Which of our saved entries to remove first to make room for `keep`: anything missing
+/// from the index, which must be from before it existed, then the least recently used
+fn eviction_order(stored: &[String], index: &[String], keep: &str) -> Vec<String> {
+    let unindexed = stored.iter().filter(|key| !index.contains(key));
+    let indexed = index.iter().filter(|key| stored.contains(key));
+    unindexed
+        .chain(indexed)
+        .filter(|key| *key != keep)
+        .cloned()
+        .collect()
+}
+
+/// Moves a key to the most recently used end of the index, dropping anything that's no
+/// longer saved
+fn touch_storage_index(key: &str) {
+    let stored = stored_keys();
+    let mut index = storage_index();
+    index.retain(|k| k != key && stored.contains(k));
+    index.push(key.to_string());
+    if let Err(err) = LocalStorage::set(STORAGE_INDEX, &index) {
+        console::warn_1(&format!("Couldn't save the forecast cache index: {}", err).into());
+    }
+}
+
+fn storage_index() -> Vec<String> {
+    LocalStorage::get(STORAGE_INDEX).unwrap_or_default()
+}
+
+/// All our cache keys in `localStorage`, without the prefix
+fn stored_keys() -> Vec<String> {
+    let storage = LocalStorage::raw();
+    let length = storage.length().unwrap_or(0);
+    (0..length)
+        .filter_map(|index| storage.key(index).ok().flatten())
+        .filter_map(|key| key.strip_prefix(STORAGE_PREFIX).map(str::to_string))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        value.parse().unwrap()
+    }
+
+    fn headers(values: &[(reqwest::header::HeaderName, &str)]) -> HeaderMap {
+        values
+            .iter()
+            .map(|(name, value)| (name.clone(), HeaderValue::from_str(value).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_cache_key() {
+        let config = ForecastClientConfig::default();
+        let key = cache_key(&config, 53.3498, -6.2603);
+        assert_eq!(
+            key,
+            "https://openaccess.pf.api.met.ie/metno-wdb2ts?lat=53.35;long=-6.26"
+        );
+        assert_eq!(cache_key(&config, 53.3451, -6.2649), key);
+        assert_ne!(cache_key(&config, 53.3449, -6.2603), key);
+
+        assert_eq!(
+            cache_key(&config, -0.001, 0.001),
+            cache_key(&config, 0., 0.)
+        );
+
+        let other = ForecastClientConfig {
+            base_url: "https://example.com/".to_string(),
+            ..Default::default()
+        };
+        assert_ne!(cache_key(&other, 53.3498, -6.2603), key);
+
+        assert!(same_key_position((53.3498, -6.2603), (53.3451, -6.2649)));
+        assert!(!same_key_position((53.3498, -6.2603), (53.3449, -6.2603)));
+    }
+
+    #[test]
+    fn test_freshness() {
+        let now = utc("2025-01-14T11:00:00Z");
+        let entry = CachedForecast::from_response(
+            "<weatherdata/>".to_string(),
+            &headers(&[
+                (EXPIRES, "Tue, 14 Jan 2025 12:00:00 GMT"),
+                (LAST_MODIFIED, "Tue, 14 Jan 2025 10:30:00 GMT"),
+            ]),
+            now,
+        );
+        assert_eq!(entry.expires, Some(utc("2025-01-14T12:00:00Z")));
+        assert_eq!(
+            entry.last_modified.as_deref(),
+            Some("Tue, 14 Jan 2025 10:30:00 GMT")
+        );
+        assert!(entry.is_fresh(now));
+        assert!(!entry.is_fresh(utc("2025-01-14T12:00:00Z")));
+
+        // No Expires, or a junk one, means always asking again
+        let no_expiry = CachedForecast::from_response(String::new(), &HeaderMap::new(), now);
+        assert!(!no_expiry.is_fresh(now));
+        let junk = CachedForecast::from_response(String::new(), &headers(&[(EXPIRES, "0")]), now);
+        assert!(!junk.is_fresh(now));
+    }
+
+    #[test]
+    fn test_revalidated() {
+        let entry = CachedForecast {
+            xml: "<weatherdata/>".to_string(),
+            expires: Some(utc("2025-01-14T12:00:00Z")),
+            last_modified: Some("Tue, 14 Jan 2025 10:30:00 GMT".to_string()),
+            fetched_at: utc("2025-01-14T11:00:00Z"),
+        };
+        let later = utc("2025-01-14T12:30:00Z");
+        let revalidated = entry.revalidated(
+            &headers(&[(EXPIRES, "Tue, 14 Jan 2025 13:30:00 GMT")]),
+            later,
+        );
+
+        assert_eq!(revalidated.xml, entry.xml);
+        assert_eq!(revalidated.last_modified, entry.last_modified);
+        assert_eq!(revalidated.fetched_at, later);
+        assert!(revalidated.is_fresh(later));
+    }
+
+    #[test]
+    fn test_recent_entries() {
+        let entry = |minute: u32| CachedForecast {
+            xml: String::new(),
+            expires: None,
+            last_modified: None,
+            fetched_at: utc(&format!("2025-01-14T11:{:02}:00Z", minute)),
+        };
+        let mut recent = RecentEntries::default();
+        for index in 0..MEMORY_ENTRIES as u32 {
+            recent.insert(&index.to_string(), entry(index));
+        }
+        // Using the oldest makes the second oldest the one to go next
+        assert_eq!(recent.get("0"), Some(entry(0)));
+        recent.insert("new", entry(59));
+
+        assert_eq!(recent.entries.len(), MEMORY_ENTRIES);
+        assert_eq!(recent.get("1"), None);
+        assert_eq!(recent.get("0"), Some(entry(0)));
+        assert_eq!(recent.get("new"), Some(entry(59)));
+
+        // Replacing doesn't count twice
+        recent.insert("new", entry(58));
+        assert_eq!(recent.entries.len(), MEMORY_ENTRIES);
+        assert_eq!(recent.get("new"), Some(entry(58)));
+    }
+
+    #[test]
+    fn test_eviction_order() {
+        let keys = |keys: &[&str]| keys.iter().map(|key| key.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            eviction_order(
+                &keys(&["c", "old", "a", "b"]),
+                &keys(&["gone", "a", "b", "c"]),
+                "c"
+            ),
+            keys(&["old", "a", "b"])
+        );
+        assert!(eviction_order(&keys(&["a"]), &keys(&["a"]), "a").is_empty());
+    }
+
+    #[test]
+    fn test_serialise() {
+        let entry = CachedForecast {
+            xml: "<weatherdata/>".to_string(),
+            expires: Some(utc("2025-01-14T12:00:00Z")),
+            last_modified: None,
+            fetched_at: utc("2025-01-14T11:00:00Z"),
+        };
+        let json = serde_json::to_string(&entry).unwrap();
+        assert_eq!(
+            serde_json::from_str::<CachedForecast>(&json).unwrap(),
+            entry
+        );
+    }
+}
@@ -0,0 +1,575 @@
+use std::{future::Future, time::Duration};
+
+use chrono::{DateTime, Utc};
+use futures::future::{abortable, select, AbortHandle, Either};
+use gloo::timers::future::sleep;
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, IF_MODIFIED_SINCE, RETRY_AFTER},
+    Client, StatusCode,
+};
+use web_sys::console;
+
+use super::{cache::CachedForecast, error::ForecastError};
+
+/// How much of an error response body to keep for showing to the user
+const MAX_ERROR_BODY_CHARS: usize = 200;
+
+/// Where the forecast comes from when nothing else is configured
+pub const DEFAULT_BASE_URL: &str = "https://openaccess.pf.api.met.ie/metno-wdb2ts";
+
+/// How requests get to the forecast service
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ProxyMode {
+    /// Straight to the base URL
+    #[default]
+    Direct,
+    /// Through a CORS proxy that takes the target URL appended to its own,
+    /// eg. "https://cors-anywhere.herokuapp.com/"
+    Prefix(String),
+}
+
+/// Everything needed to reach the forecast service
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForecastClientConfig {
+    pub base_url: String,
+    pub proxy: ProxyMode,
+    /// Extra headers sent with every request, eg. an API key for a proxy
+    pub headers: Vec<(String, String)>,
+    pub policy: RequestPolicy,
+}
+
+impl Default for ForecastClientConfig {
+    fn default() -> Self {
+        Self {
+            base_url: DEFAULT_BASE_URL.to_string(),
+            proxy: ProxyMode::Direct,
+            headers: vec![],
+            policy: RequestPolicy::default(),
+        }
+    }
+}
+
+impl ProxyMode {
+    /// An empty proxy URL means going direct
+    pub fn from_url(url: &str) -> Self {
+        match url.trim() {
+            "" => Self::Direct,
+            url => Self::Prefix(url.to_string()),
+        }
+    }
+
+    /// The proxy's URL, or an empty string if there isn't one
+    pub fn url(&self) -> &str {
+        match self {
+            Self::Direct => "",
+            Self::Prefix(url) => url,
+        }
+    }
+}
+
+/// Reads headers written as "Name: value", one per line. Header values can't contain line
+/// breaks, so anything else, like the ';' in "text/xml; charset=utf-8", is left alone.
+pub fn parse_headers(text: &str) -> Result<Vec<(String, String)>, String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (name, value) = line
+                .split_once(':')
+                .ok_or_else(|| format!("Expected \"Name: value\", got {:?}", line))?;
+            let (name, value) = (name.trim(), value.trim());
+            HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| format!("Invalid header name: {:?}", name))?;
+            HeaderValue::from_str(value)
+                .map_err(|_| format!("Invalid value for header {:?}", name))?;
+            Ok((name.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Writes headers back out in the form `parse_headers` reads, one per line
+pub fn format_headers(headers: &[(String, String)]) -> String {
+    headers
+        .iter()
+        .map(|(name, value)| format!("{}: {}", name, value))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Reads a whole number of seconds to wait for each attempt
+pub fn parse_timeout(text: &str) -> Result<Duration, String> {
+    match text.trim().parse::<u64>() {
+        Ok(seconds) if seconds > 0 => Ok(Duration::from_secs(seconds)),
+        _ => Err(format!("Expected a timeout in seconds, got {:?}", text)),
+    }
+}
+
+/// Reads how many times to try a request, which has to be at least once
+pub fn parse_max_attempts(text: &str) -> Result<u32, String> {
+    match text.trim().parse::<u32>() {
+        Ok(attempts) if attempts > 0 => Ok(attempts),
+        _ => Err(format!("Expected at least 1 attempt, got {:?}", text)),
+    }
+}
+
+impl ForecastClientConfig {
+    /// The defaults, overridden by whatever was set when the app was built:
+    /// `FORECAST_BASE_URL`, `FORECAST_PROXY_URL`, `FORECAST_HEADERS`,
+    /// `FORECAST_TIMEOUT_SECS` and `FORECAST_MAX_ATTEMPTS`
+    pub fn from_build_env() -> Self {
+        let (config, errors) = Self::from_values(
+            option_env!("FORECAST_BASE_URL"),
+            option_env!("FORECAST_PROXY_URL"),
+            option_env!("FORECAST_HEADERS"),
+            option_env!("FORECAST_TIMEOUT_SECS"),
+            option_env!("FORECAST_MAX_ATTEMPTS"),
+        );
+        for err in errors {
+            console::warn_1(&format!("Ignoring a setting from the build: {}", err).into());
+        }
+        config
+    }
+
+    /// Also returns why any of the values were left out, if they couldn't be parsed
+    fn from_values(
+        base_url: Option<&str>,
+        proxy_url: Option<&str>,
+        headers: Option<&str>,
+        timeout_secs: Option<&str>,
+        max_attempts: Option<&str>,
+    ) -> (Self, Vec<String>) {
+        let mut config = Self::default();
+        if let Some(base_url) = base_url.map(str::trim).filter(|url| !url.is_empty()) {
+            config.base_url = base_url.to_string();
+        }
+        if let Some(proxy_url) = proxy_url {
+            config.proxy = ProxyMode::from_url(proxy_url);
+        }
+        // Bad values at build time shouldn't stop the app working, so just leave them out
+        let mut errors = vec![];
+        if let Some(headers) = headers {
+            match parse_headers(headers) {
+                Ok(headers) => config.headers = headers,
+                Err(err) => errors.push(format!("FORECAST_HEADERS: {}", err)),
+            }
+        }
+        if let Some(timeout_secs) = timeout_secs {
+            match parse_timeout(timeout_secs) {
+                Ok(timeout) => config.policy.timeout = timeout,
+                Err(err) => errors.push(format!("FORECAST_TIMEOUT_SECS: {}", err)),
+            }
+        }
+        if let Some(max_attempts) = max_attempts {
+            match parse_max_attempts(max_attempts) {
+                Ok(max_attempts) => config.policy.max_attempts = max_attempts,
+                Err(err) => errors.push(format!("FORECAST_MAX_ATTEMPTS: {}", err)),
+            }
+        }
+        (config, errors)
+    }
+
+    /// The URL to request a forecast for a position from, going through the proxy if there is
+    /// one
+    pub fn forecast_url(&self, latitude: f64, longitude: f64) -> String {
+        let url = format!(
+            "{}/locationforecast?lat={:.6};long={:.6}",
+            self.base_url.trim_end_matches('/'),
+            latitude,
+            longitude
+        );
+        match &self.proxy {
+            ProxyMode::Direct => url,
+            ProxyMode::Prefix(proxy) => format!("{}{}", proxy, url),
+        }
+    }
+}
+
+/// How long to wait for a response, and how to retry when one doesn't come
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestPolicy {
+    /// For each attempt, including reading the body
+    pub timeout: Duration,
+    /// Including the first one
+    pub max_attempts: u32,
+    /// Wait before the first retry, doubling for each one after
+    pub base_delay: Duration,
+    /// The longest we'll wait between attempts. If the server asks for longer with
+    /// `Retry-After`, we give up instead.
+    pub max_delay: Duration,
+}
+
+impl Default for RequestPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(20),
+            max_attempts: 4,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RequestPolicy {
+    /// How long to wait before the next attempt, or None to give up. `jitter` is a random
+    /// number in 0..1 which spreads retries out between half and all of the backoff.
+    pub fn retry_delay(
+        &self,
+        attempt: u32,
+        error: &ForecastError,
+        retry_after: Option<Duration>,
+        jitter: f64,
+    ) -> Option<Duration> {
+        if attempt >= self.max_attempts || !error.is_retryable() {
+            return None;
+        }
+
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay);
+        let backoff = backoff.mul_f64(0.5 + jitter.clamp(0., 1.) / 2.);
+
+        match retry_after {
+            Some(retry_after) if retry_after > self.max_delay => None,
+            Some(retry_after) => Some(retry_after.max(backoff)),
+            None => Some(backoff),
+        }
+    }
+}
+
+/// Reads `Retry-After`, which is either a number of seconds or an HTTP date
+fn retry_after(headers: &HeaderMap, now: DateTime<Utc>) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let time = DateTime::parse_from_rfc2822(value).ok()?.to_utc();
+    // A date in the past means go ahead now
+    Some((time - now).to_std().unwrap_or_default())
+}
+
+/// What went wrong with one attempt, and when the server said to try again
+struct Failure {
+    error: ForecastError,
+    retry_after: Option<Duration>,
+}
+
+impl From<ForecastError> for Failure {
+    fn from(error: ForecastError) -> Self {
+        Self {
+            error,
+            retry_after: None,
+        }
+    }
+}
+
+/// Fetches forecasts from the configured service, sharing one HTTP client between requests
+#[derive(Debug, Clone)]
+pub struct ForecastClient {
+    config: ForecastClientConfig,
+    http: Client,
+}
+
+impl ForecastClient {
+    pub fn new(config: ForecastClientConfig) -> Self {
+        Self {
+            config,
+            http: Client::new(),
+        }
+    }
+
+    pub fn config(&self) -> &ForecastClientConfig {
+        &self.config
+    }
+
+    /// Fetches the weather forecast data from MET Eireann, retrying if the service is
+    /// unreachable or busy. With a cached copy this asks the server whether it has changed,
+    /// and gets the same copy back, refreshed, if it hasn't.
+    pub async fn get_weather(
+        &self,
+        latitude: f64,
+        longitude: f64,
+        cached: Option<&CachedForecast>,
+    ) -> Result<CachedForecast, ForecastError> {
+        let mut attempt = 1;
+        loop {
+            let failure = match self.attempt(latitude, longitude, cached).await {
+                Ok(entry) => return Ok(entry),
+                Err(failure) => failure,
+            };
+            let delay = self.config.policy.retry_delay(
+                attempt,
+                &failure.error,
+                failure.retry_after,
+                js_sys::Math::random(),
+            );
+            let Some(delay) = delay else {
+                return Err(failure.error);
+            };
+
+            console::warn_1(
+                &format!(
+                    "{} Trying again in {:.1}s",
+                    failure.error,
+                    delay.as_secs_f32()
+                )
+                .into(),
+            );
+            sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// One request, given up on if it takes longer than the timeout
+    async fn attempt(
+        &self,
+        latitude: f64,
+        longitude: f64,
+        cached: Option<&CachedForecast>,
+    ) -> Result<CachedForecast, Failure> {
+        let request = Box::pin(self.request(latitude, longitude, cached));
+        let timeout = Box::pin(sleep(self.config.policy.timeout));
+
+        // Dropping the request aborts the underlying fetch
+        match select(request, timeout).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => Err(ForecastError::Network(format!(
+                "No response after {}s",
+                self.config.policy.timeout.as_secs()
+            ))
+            .into()),
+        }
+    }
+
+    async fn request(
+        &self,
+        latitude: f64,
+        longitude: f64,
+        cached: Option<&CachedForecast>,
+    ) -> Result<CachedForecast, Failure> {
+        let url = self.config.forecast_url(latitude, longitude);
+
+        let mut request = self.http.get(&url);
+        for (name, value) in &self.config.headers {
+            request = request.header(name, value);
+        }
+        if let Some(last_modified) = cached.and_then(|cached| cached.last_modified.as_ref()) {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|err| ForecastError::Network(format!("Failed to fetch data: {}", err)))?;
+
+        let status = response.status();
+        let headers = response.headers().clone();
+        let now = Utc::now();
+        if let (StatusCode::NOT_MODIFIED, Some(cached)) = (status, cached) {
+            return Ok(cached.revalidated(&headers, now));
+        }
+
+        let body = response
+            .text()
+            .await
+            .map_err(|err| ForecastError::Network(format!("Failed to read response: {}", err)))?;
+
+        // Error pages aren't forecasts, so don't let them anywhere near the parser
+        if !status.is_success() {
+            return Err(Failure {
+                error: ForecastError::HttpStatus {
+                    status: status.as_u16(),
+                    body: body.trim().chars().take(MAX_ERROR_BODY_CHARS).collect(),
+                },
+                retry_after: retry_after(&headers, now),
+            });
+        }
+
+        Ok(CachedForecast::from_response(body, &headers, now))
+    }
+}
+
+/// Wraps a request so it can be called off with the returned handle, eg. when the position
+/// changes before it finishes
+pub fn cancellable<T>(
+    request: impl Future<Output = Result<T, ForecastError>>,
+) -> (impl Future<Output = Result<T, ForecastError>>, AbortHandle) {
+    let (request, handle) = abortable(request);
+    let request = async move { request.await.unwrap_or(Err(ForecastError::Cancelled)) };
+    (request, handle)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_forecast_url() {
+        let config = ForecastClientConfig::default();
+        assert_eq!(
+            config.forecast_url(53.5, -6.25),
+            "https://openaccess.pf.api.met.ie/metno-wdb2ts/locationforecast?lat=53.500000;long=-6.250000"
+        );
+
+        let proxied = ForecastClientConfig {
+            base_url: "http://openaccess.pf.api.met.ie/metno-wdb2ts/".to_string(),
+            proxy: ProxyMode::from_url(" https://cors-anywhere.herokuapp.com/ "),
+            ..Default::default()
+        };
+        assert_eq!(
+            proxied.forecast_url(53.5, -6.25),
+            "https://cors-anywhere.herokuapp.com/http://openaccess.pf.api.met.ie/metno-wdb2ts/locationforecast?lat=53.500000;long=-6.250000"
+        );
+        assert_eq!(proxied.proxy.url(), "https://cors-anywhere.herokuapp.com/");
+        assert_eq!(ProxyMode::from_url(""), ProxyMode::Direct);
+    }
+
+    #[test]
+    fn test_parse_headers() {
+        let headers = parse_headers(
+            "X-Api-Key: abc:123\r\n\nAccept: text/xml; charset=utf-8\nCookie: a=1; b=2",
+        )
+        .unwrap();
+        assert_eq!(
+            headers,
+            vec![
+                ("X-Api-Key".to_string(), "abc:123".to_string()),
+                ("Accept".to_string(), "text/xml; charset=utf-8".to_string()),
+                ("Cookie".to_string(), "a=1; b=2".to_string())
+            ]
+        );
+        assert_eq!(parse_headers(&format_headers(&headers)).unwrap(), headers);
+        assert_eq!(parse_headers("  ").unwrap(), vec![]);
+
+        assert!(parse_headers("X-Api-Key").is_err());
+        assert!(parse_headers("Bad Name: value").is_err());
+        assert!(parse_headers("X-Api-Key: new\rline").is_err());
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let policy = RequestPolicy::default();
+        let network = ForecastError::Network("Failed to fetch data".to_string());
+        let status = |status| ForecastError::HttpStatus {
+            status,
+            body: String::new(),
+        };
+
+        // Doubling each time, spread out by the jitter, up to the maximum
+        assert_eq!(
+            policy.retry_delay(1, &network, None, 1.),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(
+            policy.retry_delay(1, &network, None, 0.),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(
+            policy.retry_delay(3, &status(503), None, 1.),
+            Some(Duration::from_secs(4))
+        );
+        let long = RequestPolicy {
+            max_attempts: 100,
+            ..policy
+        };
+        assert_eq!(
+            long.retry_delay(50, &network, None, 1.),
+            Some(Duration::from_secs(30))
+        );
+
+        // Out of attempts, or not worth trying again
+        assert_eq!(policy.retry_delay(4, &network, None, 1.), None);
+        assert_eq!(policy.retry_delay(1, &status(404), None, 1.), None);
+        assert_eq!(
+            policy.retry_delay(1, &ForecastError::Schema(String::new()), None, 1.),
+            None
+        );
+        assert_eq!(
+            policy.retry_delay(1, &ForecastError::Cancelled, None, 1.),
+            None
+        );
+
+        // Retry-After wins if it's longer, unless it's too long to wait
+        assert_eq!(
+            policy.retry_delay(1, &status(429), Some(Duration::from_secs(10)), 1.),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            policy.retry_delay(3, &status(429), Some(Duration::from_secs(1)), 1.),
+            Some(Duration::from_secs(4))
+        );
+        assert_eq!(
+            policy.retry_delay(1, &status(429), Some(Duration::from_secs(3600)), 1.),
+            None
+        );
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let now = "2025-01-14T12:00:00Z".parse().unwrap();
+        let headers = |value| {
+            let mut headers = HeaderMap::new();
+            headers.insert(RETRY_AFTER, HeaderValue::from_static(value));
+            headers
+        };
+
+        assert_eq!(
+            retry_after(&headers("120"), now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            retry_after(&headers("Tue, 14 Jan 2025 12:00:30 GMT"), now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            retry_after(&headers("Tue, 14 Jan 2025 11:00:00 GMT"), now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(retry_after(&headers("soon"), now), None);
+        assert_eq!(retry_after(&HeaderMap::new(), now), None);
+    }
+
+    #[test]
+    fn test_from_values() {
+        assert_eq!(
+            ForecastClientConfig::from_values(None, None, None, None, None),
+            (ForecastClientConfig::default(), vec![])
+        );
+
+        let (config, errors) = ForecastClientConfig::from_values(
+            Some("https://example.com/wdb2ts"),
+            Some("https://proxy.example.com/"),
+            Some("X-Api-Key: abc\nBad Name: value"),
+            Some("0"),
+            Some("lots"),
+        );
+        assert_eq!(config.base_url, "https://example.com/wdb2ts");
+        assert_eq!(
+            config.proxy,
+            ProxyMode::Prefix("https://proxy.example.com/".to_string())
+        );
+        assert!(config.headers.is_empty());
+        assert_eq!(config.policy, RequestPolicy::default());
+        assert_eq!(
+            errors,
+            vec![
+                "FORECAST_HEADERS: Invalid header name: \"Bad Name\"",
+                "FORECAST_TIMEOUT_SECS: Expected a timeout in seconds, got \"0\"",
+                "FORECAST_MAX_ATTEMPTS: Expected at least 1 attempt, got \"lots\"",
+            ]
+        );
+
+        let (config, errors) = ForecastClientConfig::from_values(
+            Some(""),
+            Some(""),
+            Some("X-Api-Key: abc\nAccept: text/xml; charset=utf-8"),
+            Some(" 5 "),
+            Some("2"),
+        );
+        assert_eq!(config.base_url, DEFAULT_BASE_URL);
+        assert_eq!(config.proxy, ProxyMode::Direct);
+        assert_eq!(config.headers.len(), 2);
+        assert_eq!(config.policy.timeout, Duration::from_secs(5));
+        assert_eq!(config.policy.max_attempts, 2);
+        assert!(errors.is_empty());
+    }
+}
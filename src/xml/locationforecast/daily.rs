@@ -0,0 +1,171 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::NaiveDate;
+
+use super::{
+    dedupe_intervals, measurement::Measurement, symbol::ForecastSymbol, timezone::DisplayZone,
+    FlatItem,
+};
+
+/// Compass points for each 45° sector, starting from north
+const COMPASS_POINTS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+
+/// One day of the forecast boiled down, in the forecast's own (metric) units
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailySummary {
+    /// Calendar day in the zone the summaries were made for
+    pub date: NaiveDate,
+    pub min_temperature: Option<f32>,
+    pub max_temperature: Option<f32>,
+    pub mean_temperature: Option<f32>,
+    /// Millimetres over the whole day
+    pub precipitation: f32,
+    /// Metres per second
+    pub max_gust: Option<f32>,
+    /// Compass point the wind comes from most often
+    pub wind_direction: Option<&'static str>,
+    /// The symbol covering most of the day
+    pub symbol: Option<ForecastSymbol>,
+}
+
+/// Everything seen so far for one day
+#[derive(Default)]
+struct DayTotals {
+    temperatures: Vec<f32>,
+    precipitation: f32,
+    max_gust: Option<f32>,
+    /// Count per compass sector
+    directions: [u32; 8],
+    /// Hours covered by each symbol
+    symbols: HashMap<ForecastSymbol, f32>,
+}
+
+impl DayTotals {
+    fn summarise(self, date: NaiveDate) -> DailySummary {
+        let temperatures = &self.temperatures;
+        let min_temperature = temperatures.iter().copied().reduce(f32::min);
+        let max_temperature = temperatures.iter().copied().reduce(f32::max);
+        let mean_temperature = (!temperatures.is_empty())
+            .then(|| temperatures.iter().sum::<f32>() / temperatures.len() as f32);
+
+        // Ties go to the first sector/symbol, so the result doesn't depend on HashMap order
+        let wind_direction = self
+            .directions
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .rev()
+            .max_by_key(|(_, count)| **count)
+            .map(|(sector, _)| COMPASS_POINTS[sector]);
+        let symbol = self
+            .symbols
+            .into_iter()
+            .max_by(|a, b| {
+                a.1.total_cmp(&b.1)
+                    .then_with(|| (b.0.weather as u8).cmp(&(a.0.weather as u8)))
+            })
+            .map(|(symbol, _)| symbol);
+
+        DailySummary {
+            date,
+            min_temperature,
+            max_temperature,
+            mean_temperature,
+            precipitation: self.precipitation,
+            max_gust: self.max_gust,
+            wind_direction,
+            symbol,
+        }
+    }
+}
+
+/// Which 45° sector a wind direction is in, as an index into `COMPASS_POINTS`
+fn compass_sector(deg: f32) -> usize {
+    ((deg.rem_euclid(360.) + 22.5) / 45.) as usize % 8
+}
+
+/// Groups the forecast by calendar day in the given zone. Intervals are deduplicated and
+/// precipitation is split hourly first, so rain over midnight counts towards both days.
+pub fn daily_summaries(items: &[FlatItem], timezone: &DisplayZone) -> Vec<DailySummary> {
+    let mut days = BTreeMap::<NaiveDate, DayTotals>::new();
+
+    for item in dedupe_intervals(items) {
+        let day = days.entry(timezone.date(&item.from)).or_default();
+        match &item.measurement {
+            Measurement::Temperature(value) => day.temperatures.push(*value),
+            Measurement::WindGust { mps } => {
+                day.max_gust = Some(day.max_gust.map_or(*mps, |gust| gust.max(*mps)));
+            }
+            Measurement::WindDirection { deg, .. } => day.directions[compass_sector(*deg)] += 1,
+            Measurement::Precipitation { value, .. } => {
+                for (time, value) in item.hourly_steps(*value) {
+                    days.entry(timezone.date(&time)).or_default().precipitation += value;
+                }
+            }
+            Measurement::Symbol { .. } => {
+                if let Some(symbol) = item.measurement.symbol() {
+                    let hours = ((item.to - item.from).num_minutes() as f32 / 60.).max(1.);
+                    *day.symbols.entry(symbol).or_default() += hours;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    days.into_iter()
+        .map(|(date, totals)| totals.summarise(date))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::xml::locationforecast::{stream_flat_items, symbol::WeatherSymbol};
+
+    const DATA: &str = include_str!("../test_data/locationforecast.xml");
+
+    #[test]
+    fn test_daily_summaries() {
+        let items = stream_flat_items(DATA).unwrap();
+        let days = daily_summaries(&items, &DisplayZone::parse("UTC").unwrap());
+
+        assert_eq!(days.len(), 1);
+        let day = &days[0];
+        assert_eq!(day.date, NaiveDate::from_ymd_opt(2025, 1, 14).unwrap());
+        assert_eq!(day.min_temperature, Some(2.1));
+        assert_eq!(day.max_temperature, Some(5.9));
+        assert!((day.mean_temperature.unwrap() - 4.52).abs() < 1e-5);
+        assert!((day.precipitation - 2.0).abs() < 1e-5);
+        assert_eq!(day.max_gust, Some(10.2));
+        assert_eq!(day.wind_direction, Some("SW"));
+        // Partly cloudy covers 6 of the 12 hours
+        assert_eq!(day.symbol.unwrap().weather, WeatherSymbol::PartlyCloud);
+    }
+
+    #[test]
+    fn test_daily_summaries_timezone() {
+        // Midnight in Karachi is 19:00 UTC, so the last temperature is the next day there and
+        // the 16:00-22:00 rain is split across the two days
+        let items = stream_flat_items(DATA).unwrap();
+        let days = daily_summaries(&items, &DisplayZone::parse("Asia/Karachi").unwrap());
+
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[1].date, NaiveDate::from_ymd_opt(2025, 1, 15).unwrap());
+        assert_eq!(days[1].min_temperature, Some(2.1));
+        assert_eq!(days[0].max_temperature, Some(5.9));
+        assert!((days[1].precipitation - 0.2).abs() < 1e-5);
+        assert!((days[0].precipitation - 1.8).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_compass_sector() {
+        let points = [
+            0., 44., 90., 135., 180., 226.4, 251.3, 315., 350., 360., -10.,
+        ]
+        .map(|deg| COMPASS_POINTS[compass_sector(deg)]);
+        assert_eq!(
+            points,
+            ["N", "NE", "E", "SE", "S", "SW", "W", "NW", "N", "N", "N"]
+        );
+    }
+}
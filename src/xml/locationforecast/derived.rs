@@ -0,0 +1,350 @@
+// Quantities worked out from the measurements rather than given in the forecast. All inputs
+// and outputs are in the forecast's own (metric) units.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+
+use super::{measurement::Measurement, units::Quantity, FlatItem, TimeSpan};
+
+/// Magnus formula coefficients, good to about 0.1% between -45°C and 60°C
+const MAGNUS_A: f32 = 6.112;
+const MAGNUS_B: f32 = 17.62;
+const MAGNUS_C: f32 = 243.12;
+/// Specific gas constant for water vapour, J/(kg K)
+const WATER_VAPOUR_GAS_CONSTANT: f32 = 461.5;
+/// The air cools about 8°C per km faster than the dewpoint as it rises
+const CLOUD_BASE_METRES_PER_DEGREE: f32 = 125.;
+
+/// Saturation vapour pressure over water in hPa
+pub fn saturation_vapour_pressure(temperature: f32) -> f32 {
+    MAGNUS_A * (MAGNUS_B * temperature / (MAGNUS_C + temperature)).exp()
+}
+
+/// Actual vapour pressure in hPa, which is the saturation pressure at the dewpoint
+pub fn vapour_pressure(dewpoint: f32) -> f32 {
+    saturation_vapour_pressure(dewpoint)
+}
+
+/// Actual vapour pressure in hPa from relative humidity, for when there's no dewpoint
+pub fn vapour_pressure_from_humidity(temperature: f32, humidity: f32) -> f32 {
+    humidity / 100. * saturation_vapour_pressure(temperature)
+}
+
+/// Grams of water vapour per m^3 of air, from the vapour pressure in hPa
+pub fn absolute_humidity(temperature: f32, vapour_pressure: f32) -> f32 {
+    // Ideal gas law, with hPa -> Pa and kg -> g cancelling out
+    vapour_pressure * 100_000. / (WATER_VAPOUR_GAS_CONSTANT * (temperature + 273.15))
+}
+
+/// Rough height of the cloud base above the ground in metres, for cumulus cloud
+pub fn cloud_base(temperature: f32, dewpoint: f32) -> f32 {
+    (temperature - dewpoint).max(0.) * CLOUD_BASE_METRES_PER_DEGREE
+}
+
+/// Wind chill in °C from the wind speed in m/s (Environment Canada/NWS 2001 formula). Only
+/// defined for cold, windy weather, at or below 10°C with wind over 4.8km/h.
+pub fn wind_chill(temperature: f32, wind_speed: f32) -> Option<f32> {
+    let kmh = wind_speed * 3.6;
+    if temperature > 10. || kmh <= 4.8 {
+        return None;
+    }
+
+    let v = kmh.powf(0.16);
+    Some(13.12 + 0.6215 * temperature - 11.37 * v + 0.3965 * temperature * v)
+}
+
+/// Heat index in °C (Rothfusz regression used by the NWS). Only defined for hot, humid
+/// weather, at or above 26.7°C (80°F) and 40% humidity.
+pub fn heat_index(temperature: f32, humidity: f32) -> Option<f32> {
+    if temperature < 26.7 || humidity < 40. {
+        return None;
+    }
+
+    // The regression is in Fahrenheit
+    let t = temperature * 9. / 5. + 32.;
+    let rh = humidity;
+    let fahrenheit = -42.379 + 2.049_015_3 * t + 10.143_331 * rh
+        - 0.224_755_4 * t * rh
+        - 6.837_83e-3 * t * t
+        - 5.481_717e-2 * rh * rh
+        + 1.228_74e-3 * t * t * rh
+        + 8.5282e-4 * t * rh * rh
+        - 1.99e-6 * t * t * rh * rh;
+    Some((fahrenheit - 32.) * 5. / 9.)
+}
+
+/// Apparent temperature in °C (Steadman, as used by the Australian Bureau of Meteorology),
+/// from the vapour pressure in hPa and wind speed in m/s. Unlike wind chill and heat index,
+/// this is defined for any weather.
+pub fn apparent_temperature(temperature: f32, vapour_pressure: f32, wind_speed: f32) -> f32 {
+    temperature + 0.33 * vapour_pressure - 0.70 * wind_speed - 4.00
+}
+
+/// What it feels like: wind chill when it's cold and windy, heat index when it's hot and
+/// humid, and otherwise just the temperature
+pub fn feels_like(temperature: f32, humidity: Option<f32>, wind_speed: Option<f32>) -> f32 {
+    wind_speed
+        .and_then(|wind_speed| wind_chill(temperature, wind_speed))
+        .or_else(|| humidity.and_then(|humidity| heat_index(temperature, humidity)))
+        .unwrap_or(temperature)
+}
+
+/// The quantities this module works out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Derived {
+    ApparentTemperature,
+    FeelsLike,
+    WindChill,
+    HeatIndex,
+    CloudBase,
+    AbsoluteHumidity,
+    VapourPressure,
+}
+
+impl Derived {
+    /// Chart key, in the same style as the forecast's element names
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::ApparentTemperature => "apparentTemperature",
+            Self::FeelsLike => "feelsLike",
+            Self::WindChill => "windChill",
+            Self::HeatIndex => "heatIndex",
+            Self::CloudBase => "cloudBase",
+            Self::AbsoluteHumidity => "absoluteHumidity",
+            Self::VapourPressure => "vapourPressure",
+        }
+    }
+
+    /// What the value measures, if it's something that can be shown in other units
+    pub fn quantity(&self) -> Option<Quantity> {
+        match self {
+            Self::ApparentTemperature | Self::FeelsLike | Self::WindChill | Self::HeatIndex => {
+                Some(Quantity::Temperature)
+            }
+            Self::VapourPressure => Some(Quantity::Pressure),
+            Self::CloudBase | Self::AbsoluteHumidity => None,
+        }
+    }
+
+    /// Label for values without a `quantity`
+    pub fn unit_label(&self) -> Option<&'static str> {
+        match self {
+            Self::CloudBase => Some("Metres"),
+            Self::AbsoluteHumidity => Some("Grams per m^3"),
+            _ => None,
+        }
+    }
+}
+
+/// The measurements at one point in time which the derived values are worked out from
+#[derive(Debug, Default)]
+struct Inputs {
+    temperature: Option<f32>,
+    dewpoint: Option<f32>,
+    humidity: Option<f32>,
+    wind_speed: Option<f32>,
+}
+
+impl Inputs {
+    fn derive(&self) -> Vec<(Derived, f32)> {
+        let Some(temperature) = self.temperature else {
+            return vec![];
+        };
+        let vapour_pressure = self.dewpoint.map(vapour_pressure).or_else(|| {
+            self.humidity
+                .map(|humidity| vapour_pressure_from_humidity(temperature, humidity))
+        });
+
+        let mut values = vec![(
+            Derived::FeelsLike,
+            feels_like(temperature, self.humidity, self.wind_speed),
+        )];
+        if let Some(wind_chill) = self.wind_speed.and_then(|x| wind_chill(temperature, x)) {
+            values.push((Derived::WindChill, wind_chill));
+        }
+        if let Some(heat_index) = self.humidity.and_then(|x| heat_index(temperature, x)) {
+            values.push((Derived::HeatIndex, heat_index));
+        }
+        if let Some(dewpoint) = self.dewpoint {
+            values.push((Derived::CloudBase, cloud_base(temperature, dewpoint)));
+        }
+        if let Some(vapour_pressure) = vapour_pressure {
+            values.push((Derived::VapourPressure, vapour_pressure));
+            values.push((
+                Derived::AbsoluteHumidity,
+                absolute_humidity(temperature, vapour_pressure),
+            ));
+            if let Some(wind_speed) = self.wind_speed {
+                values.push((
+                    Derived::ApparentTemperature,
+                    apparent_temperature(temperature, vapour_pressure, wind_speed),
+                ));
+            }
+        }
+
+        values
+    }
+}
+
+/// Works out everything we can from the instantaneous measurements, in time order. Values
+/// that don't apply at a given time (eg. wind chill on a warm day) are left out.
+pub fn derive(items: &[&FlatItem]) -> Vec<(DateTime<Utc>, Derived, f32)> {
+    let mut inputs = BTreeMap::<DateTime<Utc>, Inputs>::new();
+    for item in items {
+        if item.span() != TimeSpan::Instant {
+            continue;
+        }
+        let input = inputs.entry(item.from).or_default();
+        match item.measurement {
+            Measurement::Temperature(value) => input.temperature = Some(value),
+            Measurement::DewpointTemperature(value) => input.dewpoint = Some(value),
+            Measurement::Humidity(value) => input.humidity = Some(value),
+            Measurement::WindSpeed { mps, .. } => input.wind_speed = Some(mps),
+            _ => {}
+        }
+    }
+
+    inputs
+        .into_iter()
+        .flat_map(|(time, input)| {
+            input
+                .derive()
+                .into_iter()
+                .map(move |(derived, value)| (time, derived, value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32, tolerance: f32) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "Expected {} ± {}, got {}",
+            expected,
+            tolerance,
+            actual
+        );
+    }
+
+    #[test]
+    fn test_vapour_pressure() {
+        // Reference saturation pressures over water (WMO tables)
+        assert_close(saturation_vapour_pressure(0.), 6.112, 0.001);
+        assert_close(saturation_vapour_pressure(10.), 12.28, 0.03);
+        assert_close(saturation_vapour_pressure(20.), 23.39, 0.08);
+        assert_close(saturation_vapour_pressure(30.), 42.46, 0.15);
+        assert_close(saturation_vapour_pressure(-10.), 2.865, 0.02);
+
+        assert_close(vapour_pressure(10.), saturation_vapour_pressure(10.), 0.);
+        assert_close(vapour_pressure_from_humidity(20., 50.), 11.69, 0.05);
+    }
+
+    #[test]
+    fn test_absolute_humidity() {
+        // Saturated air holds about 17.3g/m^3 at 20°C and 30.4g/m^3 at 30°C
+        assert_close(
+            absolute_humidity(20., saturation_vapour_pressure(20.)),
+            17.3,
+            0.1,
+        );
+        assert_close(
+            absolute_humidity(30., saturation_vapour_pressure(30.)),
+            30.4,
+            0.2,
+        );
+        assert_close(absolute_humidity(20., 0.), 0., 0.);
+    }
+
+    #[test]
+    fn test_cloud_base() {
+        assert_close(cloud_base(15., 10.), 625., 0.);
+        assert_close(cloud_base(10., 10.), 0., 0.);
+        // Dewpoint can't really be above the temperature, but don't go underground if it is
+        assert_close(cloud_base(10., 11.), 0., 0.);
+    }
+
+    #[test]
+    fn test_wind_chill() {
+        // Environment Canada's wind chill chart
+        assert_close(wind_chill(-10., 30. / 3.6).unwrap(), -20., 0.5);
+        assert_close(wind_chill(0., 20. / 3.6).unwrap(), -5., 0.5);
+        assert_close(wind_chill(-20., 50. / 3.6).unwrap(), -35., 0.5);
+
+        assert_eq!(wind_chill(15., 10.), None);
+        assert_eq!(wind_chill(0., 1.), None);
+    }
+
+    #[test]
+    fn test_heat_index() {
+        // NWS heat index chart: 90°F and 50% feels like 95°F, 100°F and 40% like 109°F
+        assert_close(heat_index(32.22, 50.).unwrap(), 35., 0.5);
+        assert_close(heat_index(37.78, 40.).unwrap(), 42.8, 0.5);
+
+        assert_eq!(heat_index(20., 90.), None);
+        assert_eq!(heat_index(35., 20.), None);
+    }
+
+    #[test]
+    fn test_apparent_temperature() {
+        // Worked by hand: 25 + 0.33 * 15 - 0.7 * 2 - 4
+        assert_close(apparent_temperature(25., 15., 2.), 24.55, 1e-4);
+        // Calm dry air feels 4°C cooler than it is
+        assert_close(apparent_temperature(10., 0., 0.), 6., 1e-4);
+    }
+
+    #[test]
+    fn test_feels_like() {
+        assert_close(
+            feels_like(-10., Some(80.), Some(30. / 3.6)),
+            wind_chill(-10., 30. / 3.6).unwrap(),
+            0.,
+        );
+        assert_close(
+            feels_like(32.22, Some(50.), Some(1.)),
+            heat_index(32.22, 50.).unwrap(),
+            0.,
+        );
+        assert_close(feels_like(18., Some(50.), Some(3.)), 18., 0.);
+        assert_close(feels_like(18., None, None), 18., 0.);
+    }
+
+    #[test]
+    fn test_derive() {
+        let time = "2025-01-14T11:00:00Z".parse().unwrap();
+        let item = |measurement| FlatItem {
+            from: time,
+            to: time,
+            location: Default::default(),
+            measurement,
+        };
+        let items = [
+            item(Measurement::Temperature(4.6)),
+            item(Measurement::DewpointTemperature(2.6)),
+            item(Measurement::Humidity(87.)),
+            item(Measurement::WindSpeed {
+                mps: 4.1,
+                beaufort: Some(3),
+            }),
+        ];
+        let derived = derive(&items.iter().collect::<Vec<_>>());
+
+        let kinds = derived.iter().map(|x| x.1).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                Derived::FeelsLike,
+                Derived::WindChill,
+                Derived::CloudBase,
+                Derived::VapourPressure,
+                Derived::AbsoluteHumidity,
+                Derived::ApparentTemperature
+            ]
+        );
+        assert!(derived.iter().all(|x| x.0 == time));
+        assert_close(derived[2].2, 250., 1e-3);
+    }
+}
@@ -0,0 +1,75 @@
+use std::fmt;
+
+use crate::xml::generic::XmlError;
+#[cfg(test)]
+use crate::xml::select::SelectorError;
+
+/// Everything that can go wrong between requesting a forecast and having plottable data
+#[derive(Debug, Clone, PartialEq)]
+pub enum ForecastError {
+    /// The request couldn't be sent, or the response body couldn't be read
+    Network(String),
+    /// The server responded, but not with a 2xx status
+    HttpStatus { status: u16, body: String },
+    /// The response isn't well-formed XML
+    Parse(XmlError),
+    /// The XML is fine but doesn't look like a locationforecast document
+    Schema(String),
+    /// The request was called off before it finished, eg. because the position changed
+    Cancelled,
+}
+
+impl fmt::Display for ForecastError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Network(message) => write!(f, "Couldn't reach the forecast service: {}", message),
+            Self::HttpStatus { status, body } if body.is_empty() => {
+                write!(f, "Forecast service responded with HTTP {}", status)
+            }
+            Self::HttpStatus { status, body } => {
+                write!(
+                    f,
+                    "Forecast service responded with HTTP {}: {}",
+                    status, body
+                )
+            }
+            Self::Parse(err) => write!(f, "Forecast response isn't valid XML. {}", err),
+            Self::Schema(message) => write!(f, "Unexpected forecast format: {}", message),
+            Self::Cancelled => write!(f, "Forecast request was cancelled"),
+        }
+    }
+}
+
+impl ForecastError {
+    /// Whether trying the same request again might work: network trouble, rate limiting and
+    /// server errors
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Network(_) => true,
+            Self::HttpStatus { status, .. } => *status == 429 || (500..600).contains(status),
+            _ => false,
+        }
+    }
+}
+
+impl std::error::Error for ForecastError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Parse(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<XmlError> for ForecastError {
+    fn from(err: XmlError) -> Self {
+        Self::Parse(err)
+    }
+}
+
+#[cfg(test)]
+impl From<SelectorError> for ForecastError {
+    fn from(err: SelectorError) -> Self {
+        Self::Schema(err.to_string())
+    }
+}
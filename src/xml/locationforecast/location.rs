@@ -0,0 +1,94 @@
+use std::{collections::HashMap, fmt};
+
+/// Mean radius of the earth, in km
+const EARTH_RADIUS_KM: f64 = 6371.;
+
+/// The `<location>` a measurement belongs to. This is the grid point the API snapped to, which
+/// usually isn't exactly where was asked for.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GridPoint {
+    pub id: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// Metres above sea level
+    pub altitude: Option<f32>,
+}
+
+impl GridPoint {
+    /// Reads the attributes off a `<location>` element. They're all optional, and anything
+    /// that doesn't parse is left out rather than failing the whole forecast.
+    pub fn from_attributes(attrs: &HashMap<String, String>) -> Self {
+        Self {
+            id: attrs.get("id").cloned(),
+            latitude: attrs.get("latitude").and_then(|x| x.parse().ok()),
+            longitude: attrs.get("longitude").and_then(|x| x.parse().ok()),
+            altitude: attrs.get("altitude").and_then(|x| x.parse().ok()),
+        }
+    }
+
+    /// Great-circle distance to the given coordinates, if we know where this point is
+    pub fn distance_km(&self, latitude: f64, longitude: f64) -> Option<f64> {
+        let (lat1, lon1) = (self.latitude?.to_radians(), self.longitude?.to_radians());
+        let (lat2, lon2) = (latitude.to_radians(), longitude.to_radians());
+
+        // Haversine formula
+        let a = ((lat2 - lat1) / 2.).sin().powi(2)
+            + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.).sin().powi(2);
+        Some(2. * EARTH_RADIUS_KM * a.sqrt().asin())
+    }
+}
+
+impl fmt::Display for GridPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.latitude, self.longitude) {
+            (Some(lat), Some(lon)) => write!(f, "lat={:.4}, lon={:.4}", lat, lon)?,
+            _ => write!(f, "unknown position")?,
+        }
+        if let Some(altitude) = self.altitude {
+            write!(f, ", altitude {}m", altitude)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_attributes() {
+        let attrs = [
+            ("altitude", "60"),
+            ("latitude", "53.3627"),
+            ("longitude", "-6.3111"),
+            ("id", "1"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+        let point = GridPoint::from_attributes(&attrs);
+
+        assert_eq!(point.latitude, Some(53.3627));
+        assert_eq!(point.altitude, Some(60.));
+        assert_eq!(point.to_string(), "lat=53.3627, lon=-6.3111, altitude 60m");
+        assert_eq!(
+            GridPoint::from_attributes(&HashMap::new()),
+            GridPoint::default()
+        );
+    }
+
+    #[test]
+    fn test_distance_km() {
+        let dublin = GridPoint {
+            latitude: Some(53.3498),
+            longitude: Some(-6.2603),
+            ..Default::default()
+        };
+
+        assert_eq!(dublin.distance_km(53.3498, -6.2603), Some(0.));
+        // Dublin to Cork is about 220km as the crow flies
+        let cork = dublin.distance_km(51.8985, -8.4756).unwrap();
+        assert!((cork - 220.).abs() < 5., "Distance was {}", cork);
+        assert_eq!(GridPoint::default().distance_km(0., 0.), None);
+    }
+}
@@ -0,0 +1,510 @@
+use std::collections::HashMap;
+
+use super::{
+    error::ForecastError,
+    symbol::ForecastSymbol,
+    units::{Quantity, Units},
+};
+
+/// A single parsed measurement from inside a `<location>` element
+#[derive(Debug, Clone, PartialEq)]
+pub enum Measurement {
+    /// Celsius
+    Temperature(f32),
+    /// Celsius
+    DewpointTemperature(f32),
+    /// Relative humidity, percent
+    Humidity(f32),
+    /// hPa
+    Pressure(f32),
+    /// Metres per second
+    WindSpeed {
+        mps: f32,
+        beaufort: Option<u8>,
+    },
+    /// Metres per second
+    WindGust {
+        mps: f32,
+    },
+    /// Degrees clockwise from north, plus the compass point name if given
+    WindDirection {
+        deg: f32,
+        compass: Option<String>,
+    },
+    /// Total cloud cover, percent
+    Cloudiness(f32),
+    LowClouds(f32),
+    MediumClouds(f32),
+    HighClouds(f32),
+    /// Watts per m^2
+    GlobalRadiation(f32),
+    /// Millimetres over the interval, with probability as a percent
+    Precipitation {
+        value: f32,
+        min: Option<f32>,
+        max: Option<f32>,
+        probability: Option<f32>,
+    },
+    /// Index of an icon, eg. a cloud
+    Symbol {
+        id: String,
+        number: u32,
+    },
+    /// Anything we don't know how to read, kept as-is
+    Unknown {
+        name: String,
+        attrs: HashMap<String, String>,
+    },
+}
+
+/// One plottable number pulled out of a measurement
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeriesValue {
+    /// Which chart this belongs on
+    pub key: String,
+    pub unit: Option<String>,
+    pub value: f32,
+}
+
+/// Parses an optional numeric attribute
+fn parse_attr<T: std::str::FromStr>(attrs: &HashMap<String, String>, key: &str) -> Option<T> {
+    attrs.get(key).and_then(|value| value.parse().ok())
+}
+
+/// Parses an attribute which has to be there for this kind of measurement
+fn required_attr<T: std::str::FromStr>(
+    name: &str,
+    attrs: &HashMap<String, String>,
+    key: &str,
+) -> Result<T, ForecastError> {
+    let value = attrs
+        .get(key)
+        .ok_or_else(|| ForecastError::Schema(format!("<{}> has no '{}' attribute", name, key)))?;
+
+    value.parse().map_err(|_| {
+        ForecastError::Schema(format!(
+            "<{}> has a non-numeric '{}' attribute: {:?}",
+            name, key, value
+        ))
+    })
+}
+
+/// Works out what an attribute of an element we don't have a variant for measures, so
+/// eg. `<maxTemperature unit="celsius" value="7.2"/>` still goes through `Units`. An explicit
+/// unit decides it, otherwise the names are matched against the feed's conventions. Units
+/// that aren't the metric ones we convert from are left alone.
+fn unknown_quantity(name: &str, attr: &str, unit: Option<&str>) -> Option<Quantity> {
+    if let Some(unit) = unit {
+        return match unit.to_ascii_lowercase().as_str() {
+            "celsius" => Some(Quantity::Temperature),
+            "mps" | "m/s" => Some(Quantity::Speed),
+            "mm" => Some(Quantity::Precipitation),
+            "hpa" => Some(Quantity::Pressure),
+            _ => None,
+        };
+    }
+
+    let is_value = matches!(attr, "value" | "minvalue" | "maxvalue");
+    if attr == "mps" {
+        Some(Quantity::Speed)
+    } else if is_value && name.contains("Temperature") {
+        Some(Quantity::Temperature)
+    } else if is_value && name.to_ascii_lowercase().contains("precipitation") {
+        Some(Quantity::Precipitation)
+    } else if is_value && name.to_ascii_lowercase().contains("pressure") {
+        Some(Quantity::Pressure)
+    } else {
+        None
+    }
+}
+
+impl Measurement {
+    /// Builds a measurement from an element's name and attributes. Elements we don't know
+    /// about come back as `Unknown`, known ones missing the attributes we need are an error.
+    pub fn from_attributes(
+        name: String,
+        attrs: HashMap<String, String>,
+    ) -> Result<Self, ForecastError> {
+        let required = |key: &str| required_attr(&name, &attrs, key);
+
+        let measurement = match name.as_str() {
+            "temperature" => Self::Temperature(required("value")?),
+            "dewpointTemperature" => Self::DewpointTemperature(required("value")?),
+            "humidity" => Self::Humidity(required("value")?),
+            "pressure" => Self::Pressure(required("value")?),
+            "globalRadiation" => Self::GlobalRadiation(required("value")?),
+            "windSpeed" => Self::WindSpeed {
+                mps: required("mps")?,
+                beaufort: parse_attr(&attrs, "beaufort"),
+            },
+            "windGust" => Self::WindGust {
+                mps: required("mps")?,
+            },
+            "windDirection" => Self::WindDirection {
+                deg: required("deg")?,
+                compass: attrs.get("name").cloned(),
+            },
+            "cloudiness" => Self::Cloudiness(required("percent")?),
+            "lowClouds" => Self::LowClouds(required("percent")?),
+            "mediumClouds" => Self::MediumClouds(required("percent")?),
+            "highClouds" => Self::HighClouds(required("percent")?),
+            "precipitation" => Self::Precipitation {
+                value: required("value")?,
+                min: parse_attr(&attrs, "minvalue"),
+                max: parse_attr(&attrs, "maxvalue"),
+                probability: parse_attr(&attrs, "probability"),
+            },
+            "symbol" => Self::Symbol {
+                id: attrs.get("id").cloned().unwrap_or_default(),
+                number: required_attr(&name, &attrs, "number")?,
+            },
+            _ => Self::Unknown { name, attrs },
+        };
+
+        Ok(measurement)
+    }
+
+    /// Name of the XML element this came from
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Temperature(_) => "temperature",
+            Self::DewpointTemperature(_) => "dewpointTemperature",
+            Self::Humidity(_) => "humidity",
+            Self::Pressure(_) => "pressure",
+            Self::WindSpeed { .. } => "windSpeed",
+            Self::WindGust { .. } => "windGust",
+            Self::WindDirection { .. } => "windDirection",
+            Self::Cloudiness(_) => "cloudiness",
+            Self::LowClouds(_) => "lowClouds",
+            Self::MediumClouds(_) => "mediumClouds",
+            Self::HighClouds(_) => "highClouds",
+            Self::GlobalRadiation(_) => "globalRadiation",
+            Self::Precipitation { .. } => "precipitation",
+            Self::Symbol { .. } => "symbol",
+            Self::Unknown { name, .. } => name,
+        }
+    }
+
+    /// The number to plot for this measurement, if it makes sense to plot it at all
+    pub fn plot_value(&self) -> Option<f32> {
+        match self {
+            Self::Temperature(value)
+            | Self::DewpointTemperature(value)
+            | Self::Humidity(value)
+            | Self::Pressure(value)
+            | Self::Cloudiness(value)
+            | Self::LowClouds(value)
+            | Self::MediumClouds(value)
+            | Self::HighClouds(value)
+            | Self::GlobalRadiation(value)
+            | Self::WindSpeed { mps: value, .. }
+            | Self::WindGust { mps: value }
+            | Self::WindDirection { deg: value, .. }
+            | Self::Precipitation { value, .. } => Some(*value),
+            Self::Symbol { .. } | Self::Unknown { .. } => None,
+        }
+    }
+
+    /// Everything plottable in this measurement, converted to the given units. Known
+    /// measurements give their `plot_value`, unknown ones give one series per numeric
+    /// attribute so new elements still show up.
+    pub fn series(&self, units: &Units) -> Vec<SeriesValue> {
+        let Self::Unknown { name, attrs } = self else {
+            return self
+                .plot_value()
+                .map(|value| {
+                    let (value, unit) = match self.quantity() {
+                        Some(quantity) => {
+                            (units.convert(quantity, value), Some(units.label(quantity)))
+                        }
+                        None => (value, self.unit_label()),
+                    };
+                    // Accumulated values are plotted per hour, see FlatItem::hourly_steps
+                    let unit = unit.map(|unit| match self.is_accumulated() {
+                        true => format!("{} per hour", unit),
+                        false => unit.to_string(),
+                    });
+
+                    SeriesValue {
+                        key: self.name().to_string(),
+                        unit,
+                        value,
+                    }
+                })
+                .into_iter()
+                .collect();
+        };
+
+        let mut numeric = attrs
+            .iter()
+            .filter(|(key, _)| *key != "id")
+            .filter_map(|(key, value)| value.parse::<f32>().ok().map(|value| (key, value)))
+            .collect::<Vec<_>>();
+        numeric.sort_by(|a, b| a.0.cmp(b.0));
+
+        // Always keyed by attribute too, so an element doesn't jump to another chart if it
+        // gains or loses an attribute part way through the forecast
+        numeric
+            .into_iter()
+            .map(|(key, value)| {
+                let unit = attrs.get("unit").map(String::as_str);
+                let (value, unit) = match unknown_quantity(name, key, unit) {
+                    Some(quantity) => (
+                        units.convert(quantity, value),
+                        Some(units.label(quantity).to_string()),
+                    ),
+                    None => (value, unit.map(str::to_string)),
+                };
+
+                SeriesValue {
+                    key: format!("{}.{}", name, key),
+                    unit,
+                    value,
+                }
+            })
+            .collect()
+    }
+
+    /// The decoded weather symbol, if this is one we recognise
+    pub fn symbol(&self) -> Option<ForecastSymbol> {
+        match self {
+            Self::Symbol { id, number } => ForecastSymbol::decode(id, *number),
+            _ => None,
+        }
+    }
+
+    /// Amounts which add up over time, rather than eg. a min/max over the period
+    pub fn is_accumulated(&self) -> bool {
+        matches!(self, Self::Precipitation { .. })
+    }
+
+    /// What the value measures, if it's something that can be shown in other units
+    pub fn quantity(&self) -> Option<Quantity> {
+        match self {
+            Self::Temperature(_) | Self::DewpointTemperature(_) => Some(Quantity::Temperature),
+            Self::WindSpeed { .. } | Self::WindGust { .. } => Some(Quantity::Speed),
+            Self::Precipitation { .. } => Some(Quantity::Precipitation),
+            Self::Pressure(_) => Some(Quantity::Pressure),
+            _ => None,
+        }
+    }
+
+    /// Label for the y axis when plotting `plot_value` as it comes in the forecast
+    pub fn unit_label(&self) -> Option<&'static str> {
+        if let Some(quantity) = self.quantity() {
+            return Some(Units::metric().label(quantity));
+        }
+
+        match self {
+            Self::WindDirection { .. } => Some("Degrees"),
+            Self::GlobalRadiation(_) => Some("Watts per m^2"),
+            Self::Humidity(_)
+            | Self::Cloudiness(_)
+            | Self::LowClouds(_)
+            | Self::MediumClouds(_)
+            | Self::HighClouds(_) => Some("Percent"),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn attrs(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_from_attributes() {
+        assert_eq!(
+            Measurement::from_attributes(
+                "temperature".to_string(),
+                attrs(&[("unit", "celsius"), ("value", "4.6")])
+            )
+            .unwrap(),
+            Measurement::Temperature(4.6)
+        );
+        assert_eq!(
+            Measurement::from_attributes(
+                "windSpeed".to_string(),
+                attrs(&[("mps", "4.1"), ("beaufort", "3"), ("name", "Lett bris")])
+            )
+            .unwrap(),
+            Measurement::WindSpeed {
+                mps: 4.1,
+                beaufort: Some(3)
+            }
+        );
+        assert_eq!(
+            Measurement::from_attributes(
+                "windDirection".to_string(),
+                attrs(&[("deg", "251.3"), ("name", "W")])
+            )
+            .unwrap(),
+            Measurement::WindDirection {
+                deg: 251.3,
+                compass: Some("W".to_string())
+            }
+        );
+        assert_eq!(
+            Measurement::from_attributes(
+                "precipitation".to_string(),
+                attrs(&[("value", "0.1"), ("minvalue", "0.0"), ("probability", "15")])
+            )
+            .unwrap(),
+            Measurement::Precipitation {
+                value: 0.1,
+                min: Some(0.),
+                max: None,
+                probability: Some(15.)
+            }
+        );
+        assert_eq!(
+            Measurement::from_attributes(
+                "symbol".to_string(),
+                attrs(&[("id", "Drizzle"), ("number", "46")])
+            )
+            .unwrap(),
+            Measurement::Symbol {
+                id: "Drizzle".to_string(),
+                number: 46
+            }
+        );
+    }
+
+    #[test]
+    fn test_from_attributes_unknown() {
+        let fog =
+            Measurement::from_attributes("fog".to_string(), attrs(&[("percent", "3.0")])).unwrap();
+        assert_eq!(fog.name(), "fog");
+        assert_eq!(fog.plot_value(), None);
+        assert_eq!(
+            fog.series(&Units::metric()),
+            vec![SeriesValue {
+                key: "fog.percent".to_string(),
+                unit: None,
+                value: 3.
+            }]
+        );
+
+        let max_temperature = Measurement::from_attributes(
+            "maxTemperature".to_string(),
+            attrs(&[("id", "TTT"), ("unit", "celsius"), ("value", "7.2")]),
+        )
+        .unwrap();
+        assert_eq!(
+            max_temperature.series(&Units::metric()),
+            vec![SeriesValue {
+                key: "maxTemperature.value".to_string(),
+                unit: Some("Celsius".to_string()),
+                value: 7.2
+            }]
+        );
+
+        let uv = Measurement::from_attributes(
+            "uvIndex".to_string(),
+            attrs(&[("value", "1.5"), ("max", "3"), ("level", "low")]),
+        )
+        .unwrap();
+        let keys = uv
+            .series(&Units::metric())
+            .into_iter()
+            .map(|x| x.key)
+            .collect::<Vec<_>>();
+        assert_eq!(keys, vec!["uvIndex.max", "uvIndex.value"]);
+
+        // Losing an attribute doesn't move the rest onto a different chart
+        let uv_later =
+            Measurement::from_attributes("uvIndex".to_string(), attrs(&[("value", "2")])).unwrap();
+        assert_eq!(uv_later.series(&Units::metric())[0].key, "uvIndex.value");
+
+        let nothing_numeric = Measurement::from_attributes(
+            "warning".to_string(),
+            attrs(&[("id", "1"), ("level", "yellow")]),
+        )
+        .unwrap();
+        assert!(nothing_numeric.series(&Units::metric()).is_empty());
+    }
+
+    #[test]
+    fn test_series_units() {
+        let wind = Measurement::WindSpeed {
+            mps: 10.,
+            beaufort: Some(5),
+        };
+        let metric = wind.series(&Units::metric());
+        assert_eq!(metric[0].value, 10.);
+        assert_eq!(metric[0].unit.as_deref(), Some("Metres per second"));
+        let imperial = wind.series(&Units::imperial());
+        assert!((imperial[0].value - 22.369).abs() < 1e-3);
+        assert_eq!(imperial[0].unit.as_deref(), Some("Miles per hour"));
+
+        let precipitation = Measurement::Precipitation {
+            value: 2.54,
+            min: None,
+            max: None,
+            probability: None,
+        };
+        let imperial = precipitation.series(&Units::imperial());
+        assert!((imperial[0].value - 0.1).abs() < 1e-6);
+        assert_eq!(imperial[0].unit.as_deref(), Some("Inches per hour"));
+
+        // Values without a unit choice come through untouched
+        let humidity = Measurement::Humidity(80.).series(&Units::imperial());
+        assert_eq!(humidity[0].value, 80.);
+        assert_eq!(humidity[0].unit.as_deref(), Some("Percent"));
+    }
+
+    #[test]
+    fn test_series_units_unknown() {
+        // Temperatures we don't have a variant for are still converted
+        let max_temperature = Measurement::from_attributes(
+            "maxTemperature".to_string(),
+            attrs(&[("id", "TTT"), ("unit", "celsius"), ("value", "10")]),
+        )
+        .unwrap();
+        let imperial = max_temperature.series(&Units::imperial());
+        assert_eq!(imperial[0].value, 50.);
+        assert_eq!(imperial[0].unit.as_deref(), Some("Fahrenheit"));
+
+        // Going by the name when there's no unit attribute
+        let min_temperature =
+            Measurement::from_attributes("minTemperature".to_string(), attrs(&[("value", "0")]))
+                .unwrap();
+        let imperial = min_temperature.series(&Units::imperial());
+        assert_eq!(imperial[0].value, 32.);
+        assert_eq!(imperial[0].unit.as_deref(), Some("Fahrenheit"));
+
+        let gust = Measurement::from_attributes("maxWindGust".to_string(), attrs(&[("mps", "10")]))
+            .unwrap();
+        assert_eq!(
+            gust.series(&Units::imperial())[0].unit.as_deref(),
+            Some("Miles per hour")
+        );
+
+        // Units we don't convert from stay as they are
+        let odd = Measurement::from_attributes(
+            "maxTemperature".to_string(),
+            attrs(&[("unit", "kelvin"), ("value", "280")]),
+        )
+        .unwrap();
+        let imperial = odd.series(&Units::imperial());
+        assert_eq!(imperial[0].value, 280.);
+        assert_eq!(imperial[0].unit.as_deref(), Some("kelvin"));
+    }
+
+    #[test]
+    fn test_from_attributes_schema_errors() {
+        let missing = Measurement::from_attributes("windSpeed".to_string(), attrs(&[]));
+        assert!(matches!(missing, Err(ForecastError::Schema(_))));
+
+        let broken =
+            Measurement::from_attributes("temperature".to_string(), attrs(&[("value", "warm")]));
+        assert!(matches!(broken, Err(ForecastError::Schema(_))));
+    }
+}
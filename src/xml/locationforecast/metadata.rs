@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
+use super::{model::Model, timezone::DisplayZone};
+
+/// When the forecast was made and which model runs it's made up of
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ForecastMetadata {
+    pub created: Option<DateTime<Utc>>,
+    /// In the order they appear, which is the order of the periods they cover
+    pub models: Vec<Model>,
+}
+
+fn parse_timestamp(attrs: &HashMap<String, String>, key: &str) -> Option<DateTime<Utc>> {
+    attrs.get(key).and_then(|value| value.parse().ok())
+}
+
+impl Model {
+    /// Reads a `<model>` element out of `<meta>`. The metadata is only informative, so a model
+    /// with missing or broken timestamps is left out rather than failing the whole forecast.
+    pub fn from_attributes(attrs: &HashMap<String, String>) -> Option<Self> {
+        Some(Self {
+            name: attrs.get("name")?.clone(),
+            termin: parse_timestamp(attrs, "termin")?,
+            runended: parse_timestamp(attrs, "runended")?,
+            nextrun: parse_timestamp(attrs, "nextrun")?,
+            from: parse_timestamp(attrs, "from")?,
+            to: parse_timestamp(attrs, "to")?,
+        })
+    }
+}
+
+impl ForecastMetadata {
+    /// Reads `created` off the `<weatherdata>` root
+    pub fn created_from_attributes(attrs: &HashMap<String, String>) -> Option<DateTime<Utc>> {
+        parse_timestamp(attrs, "created")
+    }
+
+    /// The model covering the start of the forecast, which is the one people care about
+    pub fn current_model(&self) -> Option<&Model> {
+        self.models.first()
+    }
+
+    /// Eg. "HARMONIE run 06Z, next update 12:30", with the next update in the given timezone.
+    /// Run times are conventionally given in UTC so they're left that way.
+    pub fn summary(&self, timezone: &DisplayZone) -> Option<String> {
+        let model = self.current_model()?;
+        Some(format!(
+            "{} run {}Z, next update {}",
+            model.name.to_uppercase(),
+            model.termin.format("%H"),
+            timezone.format(&model.nextrun, "%H:%M")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn attrs(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_summary() {
+        let model = Model::from_attributes(&attrs(&[
+            ("name", "harmonie"),
+            ("termin", "2025-01-14T06:00:00Z"),
+            ("runended", "2025-01-14T08:41:51Z"),
+            ("nextrun", "2025-01-14T12:30:00Z"),
+            ("from", "2025-01-14T11:00:00Z"),
+            ("to", "2025-01-16T12:00:00Z"),
+        ]))
+        .unwrap();
+        let metadata = ForecastMetadata {
+            created: None,
+            models: vec![model],
+        };
+
+        assert_eq!(
+            metadata
+                .summary(&DisplayZone::parse("UTC").unwrap())
+                .unwrap(),
+            "HARMONIE run 06Z, next update 12:30"
+        );
+        let paris = DisplayZone::parse("Europe/Paris").unwrap();
+        assert_eq!(
+            metadata.summary(&paris).unwrap(),
+            "HARMONIE run 06Z, next update 13:30"
+        );
+        assert_eq!(ForecastMetadata::default().summary(&paris), None);
+    }
+
+    #[test]
+    fn test_broken_model() {
+        let model =
+            Model::from_attributes(&attrs(&[("name", "harmonie"), ("termin", "this morning")]));
+        assert_eq!(model, None);
+    }
+}
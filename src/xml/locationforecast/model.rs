@@ -0,0 +1,169 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+/// Root of a locationforecast response
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct WeatherData {
+    #[serde(rename = "@created")]
+    pub created: DateTime<Utc>,
+    pub meta: Option<Meta>,
+    pub product: Product,
+}
+
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Meta {
+    #[serde(rename = "model", default)]
+    pub models: Vec<Model>,
+}
+
+/// A weather model run which provides the forecast for part of the time range
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Model {
+    #[serde(rename = "@name")]
+    pub name: String,
+    /// Nominal start time of the run, eg. 06Z
+    #[serde(rename = "@termin")]
+    pub termin: DateTime<Utc>,
+    #[serde(rename = "@runended")]
+    pub runended: DateTime<Utc>,
+    #[serde(rename = "@nextrun")]
+    pub nextrun: DateTime<Utc>,
+    #[serde(rename = "@from")]
+    pub from: DateTime<Utc>,
+    #[serde(rename = "@to")]
+    pub to: DateTime<Utc>,
+}
+
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Product {
+    #[serde(rename = "@class")]
+    pub class: String,
+    #[serde(rename = "time", default)]
+    pub times: Vec<Time>,
+}
+
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Time {
+    #[serde(rename = "@datatype")]
+    pub datatype: Option<String>,
+    #[serde(rename = "@from")]
+    pub from: DateTime<Utc>,
+    #[serde(rename = "@to")]
+    pub to: DateTime<Utc>,
+    #[serde(rename = "location", default)]
+    pub locations: Vec<Location>,
+}
+
+/// Measurements for a single grid point. Point-in-time blocks fill in the instantaneous
+/// fields, interval blocks fill in precipitation, symbol and min/max temperature.
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Location {
+    #[serde(rename = "@id")]
+    pub id: Option<String>,
+    #[serde(rename = "@latitude")]
+    pub latitude: f64,
+    #[serde(rename = "@longitude")]
+    pub longitude: f64,
+    #[serde(rename = "@altitude")]
+    pub altitude: Option<f32>,
+
+    pub temperature: Option<UnitValue>,
+    pub dewpoint_temperature: Option<UnitValue>,
+    pub min_temperature: Option<UnitValue>,
+    pub max_temperature: Option<UnitValue>,
+    pub wind_direction: Option<WindDirection>,
+    pub wind_speed: Option<WindSpeed>,
+    pub wind_gust: Option<WindSpeed>,
+    pub global_radiation: Option<UnitValue>,
+    pub humidity: Option<UnitValue>,
+    pub pressure: Option<UnitValue>,
+    pub cloudiness: Option<Percent>,
+    pub low_clouds: Option<Percent>,
+    pub medium_clouds: Option<Percent>,
+    pub high_clouds: Option<Percent>,
+    pub fog: Option<Percent>,
+    pub precipitation: Option<Precipitation>,
+    pub symbol: Option<Symbol>,
+}
+
+/// Eg. `<temperature id="TTT" unit="celsius" value="4.6"/>`
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct UnitValue {
+    #[serde(rename = "@id")]
+    pub id: Option<String>,
+    #[serde(rename = "@unit")]
+    pub unit: Option<String>,
+    #[serde(rename = "@value")]
+    pub value: f32,
+}
+
+/// Eg. `<windDirection id="dd" deg="251.3" name="W"/>`
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct WindDirection {
+    #[serde(rename = "@id")]
+    pub id: Option<String>,
+    #[serde(rename = "@deg")]
+    pub deg: f32,
+    /// Compass point
+    #[serde(rename = "@name")]
+    pub name: Option<String>,
+}
+
+/// Eg. `<windSpeed id="ff" mps="4.1" beaufort="3" name="Lett bris"/>`. Gusts only have `mps`.
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct WindSpeed {
+    #[serde(rename = "@id")]
+    pub id: Option<String>,
+    #[serde(rename = "@mps")]
+    pub mps: f32,
+    #[serde(rename = "@beaufort")]
+    pub beaufort: Option<u8>,
+    #[serde(rename = "@name")]
+    pub name: Option<String>,
+}
+
+/// Eg. `<cloudiness id="NN" percent="99.2"/>`
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Percent {
+    #[serde(rename = "@id")]
+    pub id: Option<String>,
+    #[serde(rename = "@percent")]
+    pub percent: f32,
+}
+
+/// Eg. `<precipitation unit="mm" value="0.1" minvalue="0.0" maxvalue="0.2" probability="15"/>`
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Precipitation {
+    #[serde(rename = "@unit")]
+    pub unit: Option<String>,
+    #[serde(rename = "@value")]
+    pub value: f32,
+    #[serde(rename = "@minvalue")]
+    pub minvalue: Option<f32>,
+    #[serde(rename = "@maxvalue")]
+    pub maxvalue: Option<f32>,
+    /// Percent chance of any precipitation
+    #[serde(rename = "@probability")]
+    pub probability: Option<f32>,
+}
+
+/// Eg. `<symbol id="Drizzle" number="46"/>`
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Symbol {
+    #[serde(rename = "@id")]
+    pub id: String,
+    #[serde(rename = "@number")]
+    pub number: u32,
+}
@@ -0,0 +1,290 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+
+/// The weather shown by a forecast `<symbol>`, eg. `<symbol id="LightRainSun" number="5"/>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WeatherSymbol {
+    Sun,
+    LightCloud,
+    PartlyCloud,
+    Cloud,
+    Fog,
+    LightRainSun,
+    DrizzleSun,
+    RainSun,
+    SleetSun,
+    LightSleetSun,
+    HeavySleetSun,
+    SnowSun,
+    LightSnowSun,
+    HeavySnowSun,
+    LightRain,
+    Drizzle,
+    Rain,
+    Sleet,
+    LightSleet,
+    HeavySleet,
+    Snow,
+    LightSnow,
+    HeavySnow,
+    LightRainThunderSun,
+    DrizzleThunderSun,
+    RainThunderSun,
+    SleetSunThunder,
+    LightSleetThunderSun,
+    HeavySleetThunderSun,
+    SnowSunThunder,
+    LightSnowThunderSun,
+    HeavySnowThunderSun,
+    LightRainThunder,
+    DrizzleThunder,
+    RainThunder,
+    SleetThunder,
+    LightSleetThunder,
+    HeavySleetThunder,
+    SnowThunder,
+    LightSnowThunder,
+    HeavySnowThunder,
+}
+
+/// Symbol numbers and ids as they appear in the feed
+const SYMBOLS: &[(u32, &str, WeatherSymbol)] = &[
+    (1, "Sun", WeatherSymbol::Sun),
+    (2, "LightCloud", WeatherSymbol::LightCloud),
+    (3, "PartlyCloud", WeatherSymbol::PartlyCloud),
+    (4, "Cloud", WeatherSymbol::Cloud),
+    (5, "LightRainSun", WeatherSymbol::LightRainSun),
+    (6, "LightRainThunderSun", WeatherSymbol::LightRainThunderSun),
+    (7, "SleetSun", WeatherSymbol::SleetSun),
+    (8, "SnowSun", WeatherSymbol::SnowSun),
+    (9, "LightRain", WeatherSymbol::LightRain),
+    (10, "Rain", WeatherSymbol::Rain),
+    (11, "RainThunder", WeatherSymbol::RainThunder),
+    (12, "Sleet", WeatherSymbol::Sleet),
+    (13, "Snow", WeatherSymbol::Snow),
+    (14, "SnowThunder", WeatherSymbol::SnowThunder),
+    (15, "Fog", WeatherSymbol::Fog),
+    (20, "SleetSunThunder", WeatherSymbol::SleetSunThunder),
+    (21, "SnowSunThunder", WeatherSymbol::SnowSunThunder),
+    (22, "LightRainThunder", WeatherSymbol::LightRainThunder),
+    (23, "SleetThunder", WeatherSymbol::SleetThunder),
+    (24, "DrizzleThunderSun", WeatherSymbol::DrizzleThunderSun),
+    (25, "RainThunderSun", WeatherSymbol::RainThunderSun),
+    (
+        26,
+        "LightSleetThunderSun",
+        WeatherSymbol::LightSleetThunderSun,
+    ),
+    (
+        27,
+        "HeavySleetThunderSun",
+        WeatherSymbol::HeavySleetThunderSun,
+    ),
+    (
+        28,
+        "LightSnowThunderSun",
+        WeatherSymbol::LightSnowThunderSun,
+    ),
+    (
+        29,
+        "HeavySnowThunderSun",
+        WeatherSymbol::HeavySnowThunderSun,
+    ),
+    (30, "DrizzleThunder", WeatherSymbol::DrizzleThunder),
+    (31, "LightSleetThunder", WeatherSymbol::LightSleetThunder),
+    (32, "HeavySleetThunder", WeatherSymbol::HeavySleetThunder),
+    (33, "LightSnowThunder", WeatherSymbol::LightSnowThunder),
+    (34, "HeavySnowThunder", WeatherSymbol::HeavySnowThunder),
+    (40, "DrizzleSun", WeatherSymbol::DrizzleSun),
+    (41, "RainSun", WeatherSymbol::RainSun),
+    (42, "LightSleetSun", WeatherSymbol::LightSleetSun),
+    (43, "HeavySleetSun", WeatherSymbol::HeavySleetSun),
+    (44, "LightSnowSun", WeatherSymbol::LightSnowSun),
+    (45, "HeavySnowSun", WeatherSymbol::HeavySnowSun),
+    (46, "Drizzle", WeatherSymbol::Drizzle),
+    (47, "LightSleet", WeatherSymbol::LightSleet),
+    (48, "HeavySleet", WeatherSymbol::HeavySleet),
+    (49, "LightSnow", WeatherSymbol::LightSnow),
+    (50, "HeavySnow", WeatherSymbol::HeavySnow),
+];
+
+/// Which version of the icon to show
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymbolVariant {
+    Day,
+    Night,
+    /// Polar twilight, when the sun doesn't quite rise
+    Polar,
+}
+
+/// A decoded `<symbol>`: the weather plus whether it's the day, night or polar version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ForecastSymbol {
+    pub weather: WeatherSymbol,
+    pub variant: SymbolVariant,
+}
+
+/// A weather symbol covering the period from `from` to `to`. These are drawn along the top of
+/// the temperature chart, using its time scale so they line up with the points below.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolSpan {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// When it starts, in the zone being displayed, eg. "Tue 13:00"
+    pub label: String,
+    pub symbol: ForecastSymbol,
+}
+
+impl WeatherSymbol {
+    /// The feed's name for the symbol, eg. "LightRainSun"
+    pub fn id(&self) -> &'static str {
+        SYMBOLS
+            .iter()
+            .find(|(_, _, symbol)| symbol == self)
+            .map(|(_, id, _)| *id)
+            .expect("Every symbol is in the table")
+    }
+
+    pub fn has_thunder(&self) -> bool {
+        self.id().contains("Thunder")
+    }
+
+    pub fn has_sun(&self) -> bool {
+        self.id().contains("Sun")
+    }
+
+    pub fn is_snow(&self) -> bool {
+        self.id().contains("Snow")
+    }
+
+    pub fn is_sleet(&self) -> bool {
+        self.id().contains("Sleet")
+    }
+
+    pub fn is_rain(&self) -> bool {
+        self.id().contains("Rain") || self.id().contains("Drizzle")
+    }
+}
+
+impl ForecastSymbol {
+    /// Decodes a symbol from its number, falling back to the id for numbers we don't know.
+    /// Night versions are numbered 100 higher than the day ones and polar twilight versions
+    /// 200 higher, with ids prefixed by "Dark_".
+    pub fn decode(id: &str, number: u32) -> Option<Self> {
+        let variant = match number / 100 {
+            0 => SymbolVariant::Day,
+            1 => SymbolVariant::Night,
+            _ => SymbolVariant::Polar,
+        };
+
+        let by_number = SYMBOLS
+            .iter()
+            .find(|(symbol_number, _, _)| *symbol_number == number % 100);
+        let by_id = || {
+            let id = id.strip_prefix("Dark_").unwrap_or(id);
+            SYMBOLS
+                .iter()
+                .find(|(_, symbol_id, _)| symbol_id.eq_ignore_ascii_case(id))
+        };
+
+        by_number.or_else(by_id).map(|(_, _, weather)| Self {
+            weather: *weather,
+            variant,
+        })
+    }
+
+    /// An emoji standing in for the icon
+    pub fn icon(&self) -> &'static str {
+        let weather = self.weather;
+        let dark = self.variant != SymbolVariant::Day;
+
+        if weather.has_thunder() {
+            "⛈️"
+        } else if weather.is_snow() || weather.is_sleet() {
+            "🌨️"
+        } else if weather.is_rain() && weather.has_sun() && !dark {
+            "🌦️"
+        } else if weather.is_rain() {
+            "🌧️"
+        } else {
+            match (weather, dark) {
+                (WeatherSymbol::Sun, false) => "☀️",
+                (WeatherSymbol::LightCloud, false) => "🌤️",
+                (WeatherSymbol::PartlyCloud, false) => "⛅",
+                (WeatherSymbol::Sun | WeatherSymbol::LightCloud, true) => "🌙",
+                (WeatherSymbol::Fog, _) => "🌫️",
+                _ => "☁️",
+            }
+        }
+    }
+}
+
+impl fmt::Display for ForecastSymbol {
+    /// Eg. "Light rain sun (night)", for tooltips
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let id = self.weather.id();
+        let mut words = String::new();
+        for (index, c) in id.char_indices() {
+            if index > 0 && c.is_uppercase() {
+                words.push(' ');
+                words.push(c.to_ascii_lowercase());
+            } else {
+                words.push(c);
+            }
+        }
+
+        match self.variant {
+            SymbolVariant::Day => write!(f, "{}", words),
+            SymbolVariant::Night => write!(f, "{} (night)", words),
+            SymbolVariant::Polar => write!(f, "{} (polar twilight)", words),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        let symbol = ForecastSymbol::decode("LightRainSun", 5).unwrap();
+        assert_eq!(symbol.weather, WeatherSymbol::LightRainSun);
+        assert_eq!(symbol.variant, SymbolVariant::Day);
+        assert_eq!(symbol.icon(), "🌦️");
+        assert_eq!(symbol.to_string(), "Light rain sun");
+
+        let night = ForecastSymbol::decode("Dark_Sun", 101).unwrap();
+        assert_eq!(night.weather, WeatherSymbol::Sun);
+        assert_eq!(night.variant, SymbolVariant::Night);
+        assert_eq!(night.icon(), "🌙");
+        assert_eq!(night.to_string(), "Sun (night)");
+
+        let polar = ForecastSymbol::decode("PartlyCloud", 203).unwrap();
+        assert_eq!(polar.variant, SymbolVariant::Polar);
+
+        // Unknown numbers fall back to the id
+        let fog = ForecastSymbol::decode("Fog", 99).unwrap();
+        assert_eq!(fog.weather, WeatherSymbol::Fog);
+        assert_eq!(ForecastSymbol::decode("Tornado", 99), None);
+    }
+
+    #[test]
+    fn test_table() {
+        // Every number and id is only used once
+        for (index, (number, id, symbol)) in SYMBOLS.iter().enumerate() {
+            assert!(SYMBOLS[index + 1..]
+                .iter()
+                .all(|other| other.0 != *number && other.1 != *id && other.2 != *symbol));
+            assert_eq!(symbol.id(), *id);
+            assert_eq!(
+                ForecastSymbol::decode("", *number).unwrap().weather,
+                *symbol
+            );
+        }
+
+        assert!(WeatherSymbol::SleetSunThunder.has_thunder());
+        assert!(WeatherSymbol::Drizzle.is_rain());
+        assert!(!WeatherSymbol::Cloud.is_rain());
+    }
+}
@@ -0,0 +1,130 @@
+use chrono::{DateTime, Local, NaiveDate, SecondsFormat, Utc};
+use chrono_tz::Tz;
+
+/// Which timezone to show times in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DisplayZone {
+    /// Whatever the browser is set to
+    #[default]
+    Local,
+    /// An IANA zone, eg. Europe/Dublin
+    Named(Tz),
+}
+
+impl DisplayZone {
+    /// Reads an IANA zone name, with an empty string or "local" meaning the browser's zone
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.trim() {
+            "" | "local" => Ok(Self::Local),
+            name => name
+                .parse::<Tz>()
+                .map(Self::Named)
+                .map_err(|_| format!("Unknown timezone: {:?}", name)),
+        }
+    }
+
+    /// The IANA name, if this isn't just the browser's zone
+    pub fn name(&self) -> Option<&'static str> {
+        match self {
+            Self::Local => None,
+            Self::Named(tz) => Some(tz.name()),
+        }
+    }
+
+    /// Formats a time in this zone with a chrono format string, eg. "%H:%M"
+    pub fn format(&self, time: &DateTime<Utc>, format: &str) -> String {
+        match self {
+            Self::Local => time.with_timezone(&Local).format(format).to_string(),
+            Self::Named(tz) => time.with_timezone(tz).format(format).to_string(),
+        }
+    }
+
+    /// The calendar day it is in this zone at the given time
+    pub fn date(&self, time: &DateTime<Utc>) -> NaiveDate {
+        match self {
+            Self::Local => time.with_timezone(&Local).date_naive(),
+            Self::Named(tz) => time.with_timezone(tz).date_naive(),
+        }
+    }
+
+    /// An ISO-8601 timestamp in this zone, eg. "2025-03-30T02:30:00+01:00". The offset is
+    /// worked out for each time separately, so labels either side of a DST change are right.
+    pub fn iso_label(&self, time: &DateTime<Utc>) -> String {
+        match self {
+            Self::Local => time
+                .with_timezone(&Local)
+                .to_rfc3339_opts(SecondsFormat::Secs, false),
+            Self::Named(tz) => time
+                .with_timezone(tz)
+                .to_rfc3339_opts(SecondsFormat::Secs, false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(DisplayZone::parse(""), Ok(DisplayZone::Local));
+        assert_eq!(DisplayZone::parse("local"), Ok(DisplayZone::Local));
+        assert_eq!(
+            DisplayZone::parse(" Europe/Dublin "),
+            Ok(DisplayZone::Named(chrono_tz::Europe::Dublin))
+        );
+        assert!(DisplayZone::parse("Mars/Olympus_Mons").is_err());
+        assert_eq!(
+            DisplayZone::parse("Europe/Dublin").unwrap().name(),
+            Some("Europe/Dublin")
+        );
+    }
+
+    #[test]
+    fn test_iso_label_dst() {
+        let dublin = DisplayZone::Named(chrono_tz::Europe::Dublin);
+
+        // Clocks go forward at 01:00 UTC on the last Sunday in March
+        assert_eq!(
+            dublin.iso_label(&utc("2025-03-30T00:30:00Z")),
+            "2025-03-30T00:30:00+00:00"
+        );
+        assert_eq!(
+            dublin.iso_label(&utc("2025-03-30T01:30:00Z")),
+            "2025-03-30T02:30:00+01:00"
+        );
+
+        // ...and back at 01:00 UTC on the last Sunday in October, so 01:30 happens twice
+        assert_eq!(
+            dublin.iso_label(&utc("2025-10-26T00:30:00Z")),
+            "2025-10-26T01:30:00+01:00"
+        );
+        assert_eq!(
+            dublin.iso_label(&utc("2025-10-26T01:30:00Z")),
+            "2025-10-26T01:30:00+00:00"
+        );
+
+        assert_eq!(
+            dublin.format(&utc("2025-10-26T01:30:00Z"), "%H:%M %Z"),
+            "01:30 GMT"
+        );
+
+        let utc_zone = DisplayZone::parse("UTC").unwrap();
+        assert_eq!(
+            utc_zone.iso_label(&utc("2025-01-14T11:00:00Z")),
+            "2025-01-14T11:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_iso_label_local() {
+        // Whatever zone the tests run in, the label has to point at the same instant
+        let time = utc("2025-06-01T12:00:00Z");
+        let label = DisplayZone::Local.iso_label(&time);
+        assert_eq!(label.parse::<DateTime<Utc>>().unwrap(), time);
+    }
+}
@@ -0,0 +1,394 @@
+use std::str::FromStr;
+
+/// Kinds of value which can be shown in more than one unit. The forecast always gives them in
+/// the first (metric) unit of each.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quantity {
+    Temperature,
+    Speed,
+    Precipitation,
+    Pressure,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TemperatureUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SpeedUnit {
+    #[default]
+    MetresPerSecond,
+    KilometresPerHour,
+    MilesPerHour,
+    Knots,
+    Beaufort,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PrecipitationUnit {
+    #[default]
+    Millimetres,
+    Inches,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PressureUnit {
+    #[default]
+    Hectopascals,
+    InchesOfMercury,
+}
+
+/// Upper wind speed limit of Beaufort forces 0 to 11 in m/s, anything above is force 12
+const BEAUFORT_LIMITS: [f32; 12] = [
+    0.5, 1.6, 3.4, 5.5, 8.0, 10.8, 13.9, 17.2, 20.8, 24.5, 28.5, 32.7,
+];
+
+const MPS_TO_KMH: f32 = 3.6;
+/// Exactly 1609.344m to the mile
+const MPS_TO_MPH: f32 = 3600. / 1609.344;
+/// Exactly 1852m to the nautical mile
+const MPS_TO_KNOTS: f32 = 3600. / 1852.;
+const MM_PER_INCH: f32 = 25.4;
+const HPA_PER_INHG: f32 = 33.8639;
+
+/// Lets the units be listed in a settings form and read back from it
+pub trait UnitOption: Copy + PartialEq + FromStr + 'static {
+    const ALL: &'static [Self];
+
+    /// Stable name for form values
+    fn name(&self) -> &'static str;
+
+    /// For axis titles and the settings form
+    fn label(&self) -> &'static str;
+
+    /// Abbreviation to go after a number, eg. "km/h"
+    fn symbol(&self) -> &'static str;
+}
+
+/// `FromStr` for a `UnitOption`, by its `name`
+fn parse_option<T: UnitOption>(name: &str) -> Result<T, String> {
+    T::ALL
+        .iter()
+        .find(|unit| unit.name() == name)
+        .copied()
+        .ok_or_else(|| format!("Unknown unit: {:?}", name))
+}
+
+macro_rules! unit_option {
+    ($unit:ident { $($variant:ident => ($name:literal, $label:literal, $symbol:literal)),* $(,)? }) => {
+        impl UnitOption for $unit {
+            const ALL: &'static [Self] = &[$(Self::$variant),*];
+
+            fn name(&self) -> &'static str {
+                match self {
+                    $(Self::$variant => $name),*
+                }
+            }
+
+            fn label(&self) -> &'static str {
+                match self {
+                    $(Self::$variant => $label),*
+                }
+            }
+
+            fn symbol(&self) -> &'static str {
+                match self {
+                    $(Self::$variant => $symbol),*
+                }
+            }
+        }
+
+        impl FromStr for $unit {
+            type Err = String;
+
+            fn from_str(name: &str) -> Result<Self, Self::Err> {
+                parse_option(name)
+            }
+        }
+    };
+}
+
+unit_option!(TemperatureUnit {
+    Celsius => ("celsius", "Celsius", "°C"),
+    Fahrenheit => ("fahrenheit", "Fahrenheit", "°F"),
+});
+unit_option!(SpeedUnit {
+    MetresPerSecond => ("mps", "Metres per second", "m/s"),
+    KilometresPerHour => ("kmh", "Kilometres per hour", "km/h"),
+    MilesPerHour => ("mph", "Miles per hour", "mph"),
+    Knots => ("knots", "Knots", "kn"),
+    Beaufort => ("beaufort", "Beaufort", "Bft"),
+});
+unit_option!(PrecipitationUnit {
+    Millimetres => ("mm", "Millimetres", "mm"),
+    Inches => ("in", "Inches", "in"),
+});
+unit_option!(PressureUnit {
+    Hectopascals => ("hpa", "hPa", "hPa"),
+    InchesOfMercury => ("inhg", "inHg", "inHg"),
+});
+
+impl TemperatureUnit {
+    /// Converts from Celsius
+    pub fn convert(&self, celsius: f32) -> f32 {
+        match self {
+            Self::Celsius => celsius,
+            Self::Fahrenheit => celsius * 9. / 5. + 32.,
+        }
+    }
+}
+
+impl SpeedUnit {
+    /// Converts from metres per second
+    pub fn convert(&self, mps: f32) -> f32 {
+        match self {
+            Self::MetresPerSecond => mps,
+            Self::KilometresPerHour => mps * MPS_TO_KMH,
+            Self::MilesPerHour => mps * MPS_TO_MPH,
+            Self::Knots => mps * MPS_TO_KNOTS,
+            Self::Beaufort => BEAUFORT_LIMITS
+                .iter()
+                .position(|limit| mps < *limit)
+                .unwrap_or(BEAUFORT_LIMITS.len()) as f32,
+        }
+    }
+}
+
+impl PrecipitationUnit {
+    /// Converts from millimetres
+    pub fn convert(&self, mm: f32) -> f32 {
+        match self {
+            Self::Millimetres => mm,
+            Self::Inches => mm / MM_PER_INCH,
+        }
+    }
+}
+
+impl PressureUnit {
+    /// Converts from hPa
+    pub fn convert(&self, hpa: f32) -> f32 {
+        match self {
+            Self::Hectopascals => hpa,
+            Self::InchesOfMercury => hpa / HPA_PER_INHG,
+        }
+    }
+}
+
+/// Which preset a set of units matches, if any
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitPreset {
+    Metric,
+    Imperial,
+    Custom,
+}
+
+/// The units to show values in. The presets are a starting point, each field can be changed
+/// on its own.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Units {
+    pub temperature: TemperatureUnit,
+    pub speed: SpeedUnit,
+    pub precipitation: PrecipitationUnit,
+    pub pressure: PressureUnit,
+}
+
+impl Units {
+    /// The units the forecast comes in
+    pub fn metric() -> Self {
+        Self::default()
+    }
+
+    pub fn imperial() -> Self {
+        Self {
+            temperature: TemperatureUnit::Fahrenheit,
+            speed: SpeedUnit::MilesPerHour,
+            precipitation: PrecipitationUnit::Inches,
+            pressure: PressureUnit::InchesOfMercury,
+        }
+    }
+
+    pub fn preset(&self) -> UnitPreset {
+        if *self == Self::metric() {
+            UnitPreset::Metric
+        } else if *self == Self::imperial() {
+            UnitPreset::Imperial
+        } else {
+            UnitPreset::Custom
+        }
+    }
+
+    /// Converts a value from the forecast's unit
+    pub fn convert(&self, quantity: Quantity, value: f32) -> f32 {
+        match quantity {
+            Quantity::Temperature => self.temperature.convert(value),
+            Quantity::Speed => self.speed.convert(value),
+            Quantity::Precipitation => self.precipitation.convert(value),
+            Quantity::Pressure => self.pressure.convert(value),
+        }
+    }
+
+    /// Eg. "Celsius"
+    pub fn label(&self, quantity: Quantity) -> &'static str {
+        match quantity {
+            Quantity::Temperature => self.temperature.label(),
+            Quantity::Speed => self.speed.label(),
+            Quantity::Precipitation => self.precipitation.label(),
+            Quantity::Pressure => self.pressure.label(),
+        }
+    }
+
+    /// Eg. "°C"
+    pub fn symbol(&self, quantity: Quantity) -> &'static str {
+        match quantity {
+            Quantity::Temperature => self.temperature.symbol(),
+            Quantity::Speed => self.speed.symbol(),
+            Quantity::Precipitation => self.precipitation.symbol(),
+            Quantity::Pressure => self.pressure.symbol(),
+        }
+    }
+
+    /// Converts a value from the forecast's unit and writes it out with the unit's symbol,
+    /// eg. "12.3°C"
+    pub fn format(&self, quantity: Quantity, value: f32) -> String {
+        let value = self.convert(quantity, value);
+        let symbol = self.symbol(quantity);
+        let separator = if symbol.starts_with('°') { "" } else { " " };
+        // Beaufort forces are whole numbers, and inches need the extra precision
+        let decimals = match quantity {
+            Quantity::Speed if self.speed == SpeedUnit::Beaufort => 0,
+            Quantity::Precipitation if self.precipitation == PrecipitationUnit::Inches => 2,
+            Quantity::Pressure if self.pressure == PressureUnit::InchesOfMercury => 2,
+            _ => 1,
+        };
+
+        format!("{:.*}{}{}", decimals, value, separator, symbol)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "Expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn test_temperature() {
+        let cases = [(-40., -40.), (0., 32.), (37., 98.6), (100., 212.)];
+        for (celsius, fahrenheit) in cases {
+            assert_close(TemperatureUnit::Celsius.convert(celsius), celsius);
+            assert_close(TemperatureUnit::Fahrenheit.convert(celsius), fahrenheit);
+        }
+    }
+
+    #[test]
+    fn test_speed() {
+        let cases = [
+            (SpeedUnit::MetresPerSecond, 10.),
+            (SpeedUnit::KilometresPerHour, 36.),
+            (SpeedUnit::MilesPerHour, 22.3694),
+            (SpeedUnit::Knots, 19.4384),
+            (SpeedUnit::Beaufort, 5.),
+        ];
+        for (unit, expected) in cases {
+            assert_close(unit.convert(10.), expected);
+        }
+        assert_close(SpeedUnit::MetresPerSecond.convert(0.), 0.);
+        assert_close(SpeedUnit::KilometresPerHour.convert(0.), 0.);
+    }
+
+    #[test]
+    fn test_beaufort() {
+        let cases = [
+            (0., 0.),
+            (0.49, 0.),
+            (0.5, 1.),
+            (1.6, 2.),
+            (3.4, 3.),
+            (5.5, 4.),
+            (8.0, 5.),
+            (10.8, 6.),
+            (13.9, 7.),
+            (17.2, 8.),
+            (20.8, 9.),
+            (24.5, 10.),
+            (28.5, 11.),
+            (32.6, 11.),
+            (32.7, 12.),
+            (60., 12.),
+        ];
+        for (mps, force) in cases {
+            assert_eq!(SpeedUnit::Beaufort.convert(mps), force, "{} m/s", mps);
+        }
+    }
+
+    #[test]
+    fn test_precipitation_and_pressure() {
+        assert_close(PrecipitationUnit::Millimetres.convert(25.4), 25.4);
+        assert_close(PrecipitationUnit::Inches.convert(25.4), 1.);
+        assert_close(PrecipitationUnit::Inches.convert(0.), 0.);
+
+        assert_close(PressureUnit::Hectopascals.convert(1013.25), 1013.25);
+        assert_close(PressureUnit::InchesOfMercury.convert(1013.25), 29.921);
+    }
+
+    #[test]
+    fn test_presets() {
+        assert_eq!(Units::default(), Units::metric());
+        assert_eq!(Units::metric().preset(), UnitPreset::Metric);
+        assert_eq!(Units::imperial().preset(), UnitPreset::Imperial);
+
+        let custom = Units {
+            speed: SpeedUnit::Knots,
+            ..Units::metric()
+        };
+        assert_eq!(custom.preset(), UnitPreset::Custom);
+        assert_close(custom.convert(Quantity::Speed, 1.), 1.9438);
+        assert_close(custom.convert(Quantity::Temperature, 5.), 5.);
+        assert_eq!(custom.label(Quantity::Speed), "Knots");
+
+        let imperial = Units::imperial();
+        assert_close(imperial.convert(Quantity::Temperature, 10.), 50.);
+        assert_close(imperial.convert(Quantity::Speed, 1.), 2.2369);
+        assert_close(imperial.convert(Quantity::Precipitation, 2.54), 0.1);
+        assert_close(imperial.convert(Quantity::Pressure, 33.8639), 1.);
+        assert_eq!(imperial.label(Quantity::Speed), "Miles per hour");
+        assert_eq!(imperial.label(Quantity::Temperature), "Fahrenheit");
+
+        assert_eq!(Units::metric().format(Quantity::Temperature, 4.56), "4.6°C");
+        assert_eq!(imperial.format(Quantity::Temperature, 10.), "50.0°F");
+        assert_eq!(imperial.format(Quantity::Precipitation, 2.54), "0.10 in");
+        assert_eq!(custom.format(Quantity::Speed, 10.), "19.4 kn");
+        let beaufort = Units {
+            speed: SpeedUnit::Beaufort,
+            ..Units::metric()
+        };
+        assert_eq!(beaufort.format(Quantity::Speed, 10.), "5 Bft");
+    }
+
+    #[test]
+    fn test_names() {
+        fn check<T: UnitOption + std::fmt::Debug>() {
+            for unit in T::ALL {
+                assert_eq!(unit.name().parse::<T>().ok(), Some(*unit));
+                assert!(!unit.label().is_empty());
+                assert!(!unit.symbol().is_empty());
+            }
+            assert!("nonsense".parse::<T>().is_err());
+        }
+
+        check::<TemperatureUnit>();
+        check::<SpeedUnit>();
+        check::<PrecipitationUnit>();
+        check::<PressureUnit>();
+    }
+}
@@ -1,2 +1,9 @@
+// General purpose XML tooling. The builder and path selectors are only used by the tests
+// for now, along with a few other items marked where they're defined.
+#[cfg(test)]
+pub mod builder;
 pub mod generic;
 pub mod locationforecast;
+#[cfg(test)]
+pub mod select;
+pub mod write;
@@ -0,0 +1,261 @@
+use std::{fmt, str::FromStr};
+
+use super::generic::XMLItem;
+
+/// A path through an `XMLItem` tree, eg. `product/time[datatype=forecast]/location/*`
+///
+/// Each `/`-separated step matches child elements by local name, or any element with `*`.
+/// Steps can be narrowed with attribute predicates: `[attr]` requires the attribute to be
+/// present and `[attr=value]` (value optionally quoted) requires it to have that value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selector {
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Step {
+    /// `None` matches any element
+    name: Option<String>,
+    predicates: Vec<Predicate>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Predicate {
+    attribute: String,
+    value: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectorError {
+    pub selector: String,
+    pub message: String,
+}
+
+impl fmt::Display for SelectorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid selector '{}': {}", self.selector, self.message)
+    }
+}
+
+impl std::error::Error for SelectorError {}
+
+impl Step {
+    fn matches(&self, item: &XMLItem) -> bool {
+        self.name.as_ref().is_none_or(|name| *name == item.name)
+            && self.predicates.iter().all(|predicate| {
+                match (item.attr(&predicate.attribute), &predicate.value) {
+                    (Some(actual), Some(expected)) => actual == expected,
+                    (Some(_), None) => true,
+                    (None, _) => false,
+                }
+            })
+    }
+}
+
+/// Parses the contents of a `[...]` predicate
+fn parse_predicate(body: &str) -> Result<Predicate, String> {
+    let (attribute, value) = match body.split_once('=') {
+        Some((attribute, value)) => {
+            let value = value.trim();
+            let value = ['"', '\'']
+                .iter()
+                .find_map(|quote| {
+                    value
+                        .strip_prefix(*quote)
+                        .and_then(|value| value.strip_suffix(*quote))
+                })
+                .unwrap_or(value);
+            (attribute.trim(), Some(value.to_string()))
+        }
+        None => (body.trim(), None),
+    };
+
+    if attribute.is_empty() {
+        return Err(format!("Predicate '[{}]' has no attribute name", body));
+    }
+
+    Ok(Predicate {
+        attribute: attribute.to_string(),
+        value,
+    })
+}
+
+/// Parses one `/`-separated step, eg. `time[from][datatype=forecast]`
+fn parse_step(step: &str) -> Result<Step, String> {
+    let (name, mut rest) = match step.find('[') {
+        Some(index) => step.split_at(index),
+        None => (step, ""),
+    };
+
+    let name = match name.trim() {
+        "" => return Err(format!("Step '{}' has no element name", step)),
+        "*" => None,
+        name => Some(name.to_string()),
+    };
+
+    let mut predicates = vec![];
+    while !rest.is_empty() {
+        let body = rest
+            .strip_prefix('[')
+            .and_then(|rest| rest.split_once(']'))
+            .ok_or_else(|| format!("Unclosed predicate in step '{}'", step))?;
+        predicates.push(parse_predicate(body.0)?);
+        rest = body.1.trim_start();
+    }
+
+    Ok(Step { name, predicates })
+}
+
+impl Selector {
+    pub fn parse(selector: &str) -> Result<Self, SelectorError> {
+        selector
+            .split('/')
+            .map(parse_step)
+            .collect::<Result<Vec<_>, _>>()
+            .map(|steps| Self { steps })
+            .map_err(|message| SelectorError {
+                selector: selector.to_string(),
+                message,
+            })
+    }
+
+    /// Finds every element matching this path, relative to `root`, in document order
+    pub fn all<'a>(&self, root: &'a XMLItem) -> impl Iterator<Item = &'a XMLItem> {
+        self.steps
+            .iter()
+            .fold(vec![root], |items, step| {
+                items
+                    .into_iter()
+                    .flat_map(|item| item.children().filter(|child| step.matches(child)))
+                    .collect()
+            })
+            .into_iter()
+    }
+
+    /// Finds the first element matching this path, relative to `root`
+    pub fn first<'a>(&self, root: &'a XMLItem) -> Option<&'a XMLItem> {
+        self.all(root).next()
+    }
+}
+
+impl FromStr for Selector {
+    type Err = SelectorError;
+
+    fn from_str(selector: &str) -> Result<Self, Self::Err> {
+        Self::parse(selector)
+    }
+}
+
+impl XMLItem {
+    /// Finds every element matching a `Selector` path below this one
+    pub fn select(&self, path: &str) -> Result<impl Iterator<Item = &XMLItem>, SelectorError> {
+        Ok(Selector::parse(path)?.all(self))
+    }
+
+    /// Finds the first element matching a `Selector` path below this one
+    pub fn first(&self, path: &str) -> Result<Option<&XMLItem>, SelectorError> {
+        Ok(Selector::parse(path)?.first(self))
+    }
+
+    /// Gets an attribute value by key
+    pub fn attr(&self, key: &str) -> Option<&str> {
+        self.attributes.get(key).map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::xml::generic::parse_xml;
+
+    const DATA: &str = r#"
+        <weatherdata>
+            <meta><model name="harmonie"/></meta>
+            <product class="pointData">
+                <time datatype="forecast" from="2025-01-01T00:00:00Z" to="2025-01-01T00:00:00Z">
+                    <location latitude="53.3">
+                        <temperature unit="celsius" value="4.1"/>
+                        <windSpeed mps="3.2"/>
+                    </location>
+                </time>
+                <time datatype="forecast" from="2025-01-01T00:00:00Z" to="2025-01-01T01:00:00Z">
+                    <location latitude="53.3">
+                        <precipitation unit="mm" value="0.2"/>
+                    </location>
+                </time>
+            </product>
+        </weatherdata>
+    "#;
+
+    fn names<'a>(items: impl Iterator<Item = &'a XMLItem>) -> Vec<&'a str> {
+        items.map(|item| item.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_select_path() {
+        let root = parse_xml(DATA).unwrap();
+
+        assert_eq!(
+            names(root.select("product/time/location/*").unwrap()),
+            vec!["temperature", "windSpeed", "precipitation"]
+        );
+        assert_eq!(names(root.select("*/model").unwrap()), vec!["model"]);
+        assert_eq!(root.select("product/nothing").unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_select_predicates() {
+        let root = parse_xml(DATA).unwrap();
+
+        assert_eq!(root.select("product/time[from]").unwrap().count(), 2);
+        assert_eq!(
+            names(
+                root.select("product/time[to='2025-01-01T01:00:00Z']/location/*")
+                    .unwrap()
+            ),
+            vec!["precipitation"]
+        );
+        assert_eq!(
+            names(
+                root.select("product/time/location/*[unit=celsius]")
+                    .unwrap()
+            ),
+            vec!["temperature"]
+        );
+        assert_eq!(
+            root.select("product/time/location/*[beaufort]")
+                .unwrap()
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_first_and_attr() {
+        let root = parse_xml(DATA).unwrap();
+
+        let temperature = root
+            .first("product/time/location/temperature")
+            .unwrap()
+            .unwrap();
+        assert_eq!(temperature.attr("value"), Some("4.1"));
+        assert_eq!(temperature.attr("missing"), None);
+        assert!(root.first("product/time/location/fog").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_select_invalid_path() {
+        let root = parse_xml(DATA).unwrap();
+
+        assert!(root.select("product//time").is_err());
+        assert_eq!(root.first("time[from").unwrap_err().selector, "time[from");
+    }
+
+    #[test]
+    fn test_parse_invalid_selector() {
+        assert!(Selector::parse("product//time").is_err());
+        assert!(Selector::parse("time[from").is_err());
+        assert!(Selector::parse("time[=x]").is_err());
+        assert!("product/time[ from = \"x\" ]".parse::<Selector>().is_ok());
+    }
+}
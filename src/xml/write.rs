@@ -0,0 +1,269 @@
+use quick_xml::escape::escape;
+
+use super::generic::{split_namespaced_key, XMLItem, XMLNode};
+
+/// Controls the output of `XMLItem::to_xml_string_with`
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    /// Put each element on its own line, indented by this many spaces per level. Elements
+    /// that contain text are kept on a single line so their content isn't changed.
+    pub indent: Option<usize>,
+    /// Start with an `<?xml version="1.0" encoding="UTF-8"?>` declaration
+    pub declaration: bool,
+}
+
+impl XMLItem {
+    /// Writes the element back out as compact XML. Reading it with `parse_xml` gives back the
+    /// same tree for anything from `parse_xml` or `XMLItem::builder`. Trees put together by
+    /// hand with untrimmed, empty or back to back text nodes come back tidied up instead.
+    #[cfg(test)]
+    pub fn to_xml_string(&self) -> String {
+        self.to_xml_string_with(&WriteOptions::default())
+    }
+
+    pub fn to_xml_string_with(&self, options: &WriteOptions) -> String {
+        let mut out = String::new();
+        if options.declaration {
+            out.push_str(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+            if options.indent.is_some() {
+                out.push('\n');
+            }
+        }
+        write_item(&mut out, self, None, options.indent, 0);
+
+        out
+    }
+}
+
+fn write_cdata(out: &mut String, text: &str) {
+    // "]]>" can't appear inside a CDATA section, so it has to be split across two
+    out.push_str("<![CDATA[");
+    out.push_str(&text.replace("]]>", "]]]]><![CDATA[>"));
+    out.push_str("]]>");
+}
+
+/// Writes the start tag's name, namespace declarations and attributes
+fn write_start_tag(out: &mut String, item: &XMLItem, parent_namespace: Option<&str>) {
+    out.push('<');
+    out.push_str(&item.name);
+
+    // Elements carry their namespace as the default one, redeclared whenever it changes
+    if item.namespace.as_deref() != parent_namespace {
+        out.push_str(" xmlns=\"");
+        out.push_str(&escape(item.namespace.as_deref().unwrap_or_default()));
+        out.push('"');
+    }
+
+    // Sorted so the output doesn't depend on HashMap ordering
+    let mut attributes = item.attributes.iter().collect::<Vec<_>>();
+    attributes.sort();
+
+    for (index, (key, value)) in attributes.into_iter().enumerate() {
+        out.push(' ');
+        match split_namespaced_key(key) {
+            // Namespaced attributes get a prefix declared right alongside them
+            Some((namespace, local_name)) => {
+                out.push_str(&format!(
+                    "xmlns:ns{}=\"{}\" ns{}:{}",
+                    index,
+                    escape(namespace),
+                    index,
+                    local_name
+                ));
+            }
+            None => out.push_str(key),
+        }
+        out.push_str("=\"");
+        out.push_str(&escape(value.as_str()));
+        out.push('"');
+    }
+}
+
+fn write_item(
+    out: &mut String,
+    item: &XMLItem,
+    parent_namespace: Option<&str>,
+    indent: Option<usize>,
+    depth: usize,
+) {
+    let padding = " ".repeat(indent.unwrap_or(0) * depth);
+    let newline = if indent.is_some() { "\n" } else { "" };
+
+    out.push_str(&padding);
+    write_start_tag(out, item, parent_namespace);
+
+    if item.nodes.is_empty() {
+        out.push_str("/>");
+        out.push_str(newline);
+        return;
+    }
+    out.push('>');
+
+    let namespace = item.namespace.as_deref();
+    let has_text = item
+        .nodes
+        .iter()
+        .any(|node| !matches!(node, XMLNode::Element(_)));
+
+    if indent.is_none() || has_text {
+        // Anything written between the nodes would end up as part of the text
+        item.nodes.iter().for_each(|node| match node {
+            XMLNode::Element(child) => write_item(out, child, namespace, None, 0),
+            XMLNode::Text(text) => out.push_str(&escape(text.as_str())),
+            XMLNode::CData(text) => write_cdata(out, text),
+        });
+    } else {
+        out.push_str(newline);
+        item.children()
+            .for_each(|child| write_item(out, child, namespace, indent, depth + 1));
+        out.push_str(&padding);
+    }
+
+    out.push_str("</");
+    out.push_str(&item.name);
+    out.push('>');
+    out.push_str(newline);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::xml::generic::{parse_xml, parse_xml_with, ParseOptions};
+
+    fn fixture() -> XMLItem {
+        XMLItem::builder("weatherdata")
+            .attr("created", "2025-01-14T10:02:19Z")
+            .child(
+                XMLItem::builder("product")
+                    .attr("class", "pointData")
+                    .child(
+                        XMLItem::builder("time")
+                            .attr("from", "2025-01-14T11:00:00Z")
+                            .attr("to", "2025-01-14T11:00:00Z")
+                            .child(XMLItem::builder("location").children([
+                                XMLItem::builder("temperature").attr("value", "4.6"),
+                                XMLItem::builder("windDirection").attr("name", "\"W\" & <more>"),
+                            ])),
+                    ),
+            )
+            .child(
+                XMLItem::builder("warning")
+                    .text("Wind & rain")
+                    .child(XMLItem::builder("b").text("<tonight>"))
+                    .cdata("raw <data>"),
+            )
+            .build()
+    }
+
+    #[test]
+    fn test_to_xml_string() {
+        let item = XMLItem::builder("a")
+            .attr("x", "1 < 2")
+            .child(XMLItem::new("b"))
+            .text("c & d")
+            .build();
+
+        assert_eq!(item.to_xml_string(), r#"<a x="1 &lt; 2"><b/>c &amp; d</a>"#);
+    }
+
+    #[test]
+    fn test_to_xml_string_cdata() {
+        let item = XMLItem::builder("a").cdata("x]]>y").build();
+        let root = parse_xml(&item.to_xml_string()).unwrap();
+
+        assert_eq!(item.to_xml_string(), "<a><![CDATA[x]]]]><![CDATA[>y]]></a>");
+        assert_eq!(root.text(), "x]]>y");
+    }
+
+    #[test]
+    fn test_to_xml_string_pretty() {
+        let item = XMLItem::builder("a")
+            .child(XMLItem::builder("b").child(XMLItem::new("c")))
+            .child(XMLItem::builder("d").text("text"))
+            .build();
+        let options = WriteOptions {
+            indent: Some(2),
+            declaration: true,
+        };
+
+        assert_eq!(
+            item.to_xml_string_with(&options),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<a>\n  <b>\n    <c/>\n  </b>\n  <d>text</d>\n</a>\n"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let item = fixture();
+
+        assert_eq!(parse_xml(&item.to_xml_string()).unwrap(), item);
+
+        let options = WriteOptions {
+            indent: Some(4),
+            declaration: true,
+        };
+        assert_eq!(parse_xml(&item.to_xml_string_with(&options)).unwrap(), item);
+    }
+
+    #[test]
+    fn test_round_trip_text() {
+        // The builder tidies text up the same way parsing does
+        let item = XMLItem::builder("a")
+            .text("  padded ")
+            .text("and joined  ")
+            .child(XMLItem::builder("b").text("   "))
+            .text("")
+            .cdata("")
+            .cdata(" kept as is ")
+            .text(" after")
+            .build();
+
+        assert_eq!(
+            item.nodes,
+            vec![
+                XMLNode::Text("padded and joined".to_string()),
+                XMLNode::Element(XMLItem::new("b")),
+                XMLNode::CData(" kept as is ".to_string()),
+                XMLNode::Text("after".to_string()),
+            ]
+        );
+        assert_eq!(parse_xml(&item.to_xml_string()).unwrap(), item);
+
+        // ...but a tree put together by hand doesn't get that
+        let mut untidy = XMLItem::new("a");
+        untidy.nodes = vec![
+            XMLNode::Text(" a ".to_string()),
+            XMLNode::Text("b".to_string()),
+            XMLNode::Text(String::new()),
+        ];
+        let read_back = parse_xml(&untidy.to_xml_string()).unwrap();
+        assert_ne!(read_back, untidy);
+        assert_eq!(read_back.nodes, vec![XMLNode::Text("a b".to_string())]);
+    }
+
+    #[test]
+    fn test_round_trip_forecast() {
+        let item = parse_xml(include_str!("test_data/locationforecast.xml")).unwrap();
+        assert_eq!(parse_xml(&item.to_xml_string()).unwrap(), item);
+    }
+
+    #[test]
+    fn test_round_trip_namespaces() {
+        let item = XMLItem::builder("alert")
+            .namespace("urn:oasis:names:tc:emergency:cap:1.2")
+            .attr_ns("http://www.w3.org/2001/XMLSchema-instance", "type", "x")
+            .attr("type", "y")
+            .child(XMLItem::builder("point").namespace("http://www.opengis.net/gml"))
+            .child(XMLItem::new("plain"))
+            .build();
+        let options = ParseOptions {
+            resolve_namespaces: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            parse_xml_with(&item.to_xml_string(), &options).unwrap(),
+            item
+        );
+    }
+}